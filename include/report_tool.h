@@ -0,0 +1,152 @@
+#ifndef REPORT_TOOL_H
+#define REPORT_TOOL_H
+
+/* Generated by cbindgen from src/ffi, do not edit by hand. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// Outcome of a call into the library.
+typedef enum ReportToolStatus {
+  REPORT_TOOL_STATUS_OK = 0,
+  // A required pointer argument was null
+  REPORT_TOOL_STATUS_NULL_ARGUMENT = 1,
+  // A string argument was not valid UTF-8
+  REPORT_TOOL_STATUS_INVALID_UTF8 = 2,
+  REPORT_TOOL_STATUS_METADATA_WRONG_FORMAT = 3,
+  // The csv, json or xlsx input could not be read
+  REPORT_TOOL_STATUS_CSV_INPUT_WRONG_FORMAT = 4,
+  // Invalid language or include criteria
+  REPORT_TOOL_STATUS_INVALID_CONFIG = 5,
+  // A record failed validation
+  REPORT_TOOL_STATUS_INVALID_DATA = 6,
+  REPORT_TOOL_STATUS_IO = 7,
+  REPORT_TOOL_STATUS_XLSX = 8,
+  REPORT_TOOL_STATUS_INTERNAL = 9,
+} ReportToolStatus;
+
+// Opaque handle to a loaded `DataSet`.
+typedef struct ReportToolDataSet ReportToolDataSet;
+
+// Load a `DataSet` from a metadata json file and a csv input file.
+//
+// `categories` and `values` are parallel arrays of `includes_len` include criteria,
+// e.g. `"gender"` / `"男性"`. On success `*out` receives a handle to be released with
+// `report_tool_dataset_free`.
+//
+// The csv input is read with the default options: UTF-8 or Shift_JIS as detected, comma
+// separated, the header on the first line and failing on the first invalid row. An input
+// ending in `.json`, `.ndjson`, `.jsonl` or `.xlsx` is read in that format, from the first
+// worksheet of a workbook. Other input options can not be given through the C API.
+//
+// # Safety
+//
+// Pointers must be null or valid for the documented length; strings must be NUL-terminated.
+enum ReportToolStatus report_tool_dataset_from_files(const char *meta_path,
+                                                     const char *input_path,
+                                                     const char *lang,
+                                                     uint16_t created_year,
+                                                     const char *const *categories,
+                                                     const char *const *values,
+                                                     size_t includes_len,
+                                                     struct ReportToolDataSet **out,
+                                                     char **err);
+
+// Load a `DataSet` from in-memory metadata json and csv input, both UTF-8 and not
+// NUL-terminated. Otherwise identical to `report_tool_dataset_from_files`, the input being
+// csv only.
+//
+// # Safety
+//
+// `meta` and `input` must be null or valid for reads of `meta_len` and `input_len` bytes;
+// `lang` and the include criteria must be NUL-terminated strings.
+enum ReportToolStatus report_tool_dataset_from_buffers(const uint8_t *meta,
+                                                       size_t meta_len,
+                                                       const uint8_t *input,
+                                                       size_t input_len,
+                                                       const char *lang,
+                                                       uint16_t created_year,
+                                                       const char *const *categories,
+                                                       const char *const *values,
+                                                       size_t includes_len,
+                                                       struct ReportToolDataSet **out,
+                                                       char **err);
+
+// Number of records left in the dataset after validation and filtering.
+//
+// # Safety
+//
+// `dataset` must be null or a handle returned by this library.
+size_t report_tool_dataset_len(const struct ReportToolDataSet *dataset);
+
+// Release a dataset handle. Null is ignored.
+//
+// # Safety
+//
+// `dataset` must be null or a handle returned by this library, released only once.
+void report_tool_dataset_free(struct ReportToolDataSet *dataset);
+
+// Write the full xlsx report for `dataset` to `output_path`: every sheet but the index and
+// data-quality sheets, in the default order, with the default xlsx options and theme. Sheets
+// and options can not be chosen through the C API.
+//
+// # Safety
+//
+// `dataset` must be a handle returned by this library; strings must be NUL-terminated.
+enum ReportToolStatus report_tool_write_workbook(const struct ReportToolDataSet *dataset,
+                                                 const char *output_path,
+                                                 char **err);
+
+// Write the full xlsx report for `dataset` into a buffer, as `report_tool_write_workbook`
+// does. `*out` and `*out_len` receive the workbook bytes, which must be released with
+// `report_tool_bytes_free`.
+//
+// # Safety
+//
+// `dataset` must be a handle returned by this library; `out` and `out_len` must be valid for
+// writes.
+enum ReportToolStatus report_tool_write_workbook_bytes(const struct ReportToolDataSet *dataset,
+                                                       uint8_t **out,
+                                                       size_t *out_len,
+                                                       char **err);
+
+// Load the inputs and write the full xlsx report in one call, like `create_output_file`.
+//
+// # Safety
+//
+// Pointers must be null or valid for the documented length; strings must be NUL-terminated.
+enum ReportToolStatus report_tool_create_output_file(const char *meta_path,
+                                                     const char *input_path,
+                                                     const char *output_path,
+                                                     const char *lang,
+                                                     uint16_t created_year,
+                                                     const char *const *categories,
+                                                     const char *const *values,
+                                                     size_t includes_len,
+                                                     char **err);
+
+// Available include criteria, as the json serialization of
+// `provide_include_criteria_constants`. `*out` must be released with
+// `report_tool_string_free`.
+//
+// # Safety
+//
+// `out` must be valid for writes; `err` must be null or valid for writes.
+enum ReportToolStatus report_tool_include_criteria_constants(char **out, char **err);
+
+// Release a string returned by this library. Null is ignored.
+//
+// # Safety
+//
+// `s` must be null or a string returned by this library, released only once.
+void report_tool_string_free(char *s);
+
+// Release a buffer returned by this library. Null is ignored.
+//
+// # Safety
+//
+// `bytes` must be null or a buffer returned by this library with its length `len`, released
+// only once.
+void report_tool_bytes_free(uint8_t *bytes, size_t len);
+
+#endif /* REPORT_TOOL_H */
@@ -169,6 +169,27 @@ impl IncludeCriteria {
         };
         res.to_string()
     }
+
+    /// Category and value of the criteria by their display names, as given in configs.
+    pub fn display_names(&self, lng: Language) -> (String, String) {
+        match self {
+            IncludeCriteria::PurchaseStatusInclude(v) =>
+                (PurchaseStatus::display_name_of_enum(lng), v.display_name(lng)),
+            IncludeCriteria::MaritalStatusInclude(v) =>
+                (MaritalStatus::display_name_of_enum(lng), v.display_name(lng)),
+            IncludeCriteria::GenderInclude(v) =>
+                (Gender::display_name_of_enum(lng), v.display_name(lng)),
+            IncludeCriteria::ChildrenRangeInclude(v) =>
+                (ChildrenRange::display_name_of_enum(lng), v.display_name(lng)),
+            IncludeCriteria::JobInclude(v) => (Job::display_name_of_enum(lng), v.display_name(lng)),
+            IncludeCriteria::AgeRange1070Include(v) =>
+                (AgeRange1070::display_name_of_enum(lng), v.display_name(lng)),
+            IncludeCriteria::YearlyIncomeRangeInclude(v) =>
+                (YearlyIncomeRange::display_name_of_enum(lng), v.display_name(lng)),
+            IncludeCriteria::PrefectureInclude(v) =>
+                (Prefecture::display_name_of_enum(lng), v.display_name(lng)),
+        }
+    }
 }
 
 // Constants API
@@ -0,0 +1,153 @@
+use crate::errors::RustlyzerError;
+use crate::helpers::ExtractFromStr;
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Csv header of each input column.
+///
+/// Standard fields default to the headers of the original export. Custom fields are by default
+/// every column whose header contains a number, the field id; once `custom_fields` is given,
+/// only the columns it lists.
+///
+/// ```json
+/// {
+///     "user_id": "会員ID",
+///     "gender": "性別",
+///     "birth_year": "生年",
+///     "custom_fields": { "Q1 好きな色": 1, "Q2 購入頻度": 2 }
+/// }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColumnMapping {
+    pub id: String,
+    pub user_id: String,
+    pub campaign_id: String,
+    pub price: String,
+    pub bonus_point: String,
+    pub status: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub email: String,
+    pub nickname: String,
+    pub gender: String,
+    pub birth_year: String,
+    pub job: String,
+    pub prefecture: String,
+    pub marital_status: String,
+    pub children: String,
+    pub household_income_min: String,
+    pub household_income_max: String,
+    /// Custom field id of source headers
+    pub custom_fields: BTreeMap<String, usize>,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        ColumnMapping {
+            id: "id".to_string(),
+            user_id: "user_id".to_string(),
+            campaign_id: "campaign_id".to_string(),
+            price: "price".to_string(),
+            bonus_point: "bonus_point".to_string(),
+            status: "status".to_string(),
+            created_at: "created_at".to_string(),
+            updated_at: "updated at".to_string(),
+            email: "email".to_string(),
+            nickname: "nickname".to_string(),
+            gender: "gender".to_string(),
+            birth_year: "birth_year".to_string(),
+            job: "job".to_string(),
+            prefecture: "prefecture".to_string(),
+            marital_status: "marital_status".to_string(),
+            children: "children".to_string(),
+            household_income_min: "household_income_min".to_string(),
+            household_income_max: "household_income_max".to_string(),
+            custom_fields: BTreeMap::<String, usize>::new(),
+        }
+    }
+}
+
+impl ColumnMapping {
+    pub fn from_json(content: &str) -> Result<Self, RustlyzerError> {
+        serde_json::from_str::<ColumnMapping>(content)
+            .map_err(|e| RustlyzerError::InvalidConfigItemError(format!("column mapping: {}", e)))
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, RustlyzerError> {
+        let mut content = String::new();
+        File::open(path)?.read_to_string(&mut content)?;
+        Self::from_json(&content)
+    }
+
+    /// Standard fields by the header `InputRecord` reads them from, with their source header.
+    fn standard(&self) -> Vec<(&'static str, &str)> {
+        vec![
+            ("id", &self.id),
+            ("user_id", &self.user_id),
+            ("campaign_id", &self.campaign_id),
+            ("price", &self.price),
+            ("bonus_point", &self.bonus_point),
+            ("status", &self.status),
+            ("created_at", &self.created_at),
+            ("updated at", &self.updated_at),
+            ("email", &self.email),
+            ("nickname", &self.nickname),
+            ("gender", &self.gender),
+            ("birth_year", &self.birth_year),
+            ("job", &self.job),
+            ("prefecture", &self.prefecture),
+            ("marital_status", &self.marital_status),
+            ("children", &self.children),
+            ("household_income_min", &self.household_income_min),
+            ("household_income_max", &self.household_income_max),
+        ]
+    }
+
+    /// Rename the `source` header row to the headers `InputRecord` reads. Columns mapped to no
+    /// field get an empty header and are ignored.
+    ///
+    /// Fails listing every mapped header missing from `source`, before any record is read.
+    pub(crate) fn input_headers(&self, source: &StringRecord)
+        -> Result<StringRecord, RustlyzerError> {
+        let standard = self.standard();
+        let missing = standard.iter()
+            .map(|&(name, header)| (name.to_string(), header))
+            .chain(self.custom_fields.iter()
+                .map(|(header, id)| (format!("field{}", id), header.as_str())))
+            .filter(|(_, header)| !source.iter().any(|h| h == *header))
+            .map(|(name, header)| format!("`{}` ({})", header, name))
+            .collect::<Vec<String>>();
+        if !missing.is_empty() {
+            return Err(RustlyzerError::CsvInputWrongFormat(
+                format!("missing columns {}", missing.join(", "))));
+        }
+        Ok(source.iter()
+            .map(|header| self.input_header(&standard, header))
+            .collect::<StringRecord>())
+    }
+
+    /// Header `InputRecord` reads the `source` header as, empty for a column mapped to no field.
+    /// `standard` is `self.standard()`.
+    fn input_header(&self, standard: &[(&'static str, &str)], source: &str) -> String {
+        if let Some(&(name, _)) = standard.iter().find(|&&(_, h)| h == source) {
+            return name.to_string();
+        }
+        match self.custom_fields.get(source) {
+            Some(id) => format!("field{}", id),
+            None if self.custom_fields.is_empty()
+                && usize::extract_from_str(source).is_some() => source.to_string(),
+            None => String::new(),
+        }
+    }
+
+    /// Key `InputRecord` reads the `source` key of json input as, renamed as `input_headers`
+    /// renames csv headers.
+    pub(crate) fn input_key(&self, source: &str) -> String {
+        self.input_header(&self.standard(), source)
+    }
+}
@@ -3,6 +3,7 @@ use super::{InputRecord, Data, FieldType,
 use super::enums::{AgeRange1060, YearlyIncomeRange, ChildrenRange, Job, MaritalStatus, Gender };
 use hashbrown::HashMap;
 use super::{Meta, CustomFieldVariant};
+use crate::ds::table::CellValue;
 use crate::errors::RustlyzerError;
 use serde_json::Value;
 use indexmap::map::IndexMap;
@@ -11,63 +12,65 @@ use std::ptr::null;
 
 impl Data {
     pub fn get_static_computed_field_vals(&self, field:&FieldType, year: u16, lng: Language)
-        -> Vec<String>
+        -> Vec<CellValue>
     {
         match lng {
             _ => match field {
                 FieldType::Computed(computed) => {
-                    let mut list = Vec::<String>::new();
+                    let mut list = Vec::<CellValue>::new();
                     match computed {
                         // Labels
                         ComputedFieldType::AgeGroup1060AggregateLabel => vec![
-                            "10代以下".to_string(),
-                            "20代".to_string(),
-                            "30代".to_string(),
-                            "40代".to_string(),
-                            "50代".to_string(),
-                            "60代以上".to_string(),
+                            "10代以下".into(),
+                            "20代".into(),
+                            "30代".into(),
+                            "40代".into(),
+                            "50代".into(),
+                            "60代以上".into(),
                         ],
                         ComputedFieldType::AgeGroup1070AggregateLabel => vec![
-                            "10代以下".to_string(),
-                            "20代".to_string(),
-                            "30代".to_string(),
-                            "40代".to_string(),
-                            "50代".to_string(),
-                            "60代".to_string(),
-                            "70代以上".to_string(),
+                            "10代以下".into(),
+                            "20代".into(),
+                            "30代".into(),
+                            "40代".into(),
+                            "50代".into(),
+                            "60代".into(),
+                            "70代以上".into(),
                         ],
                         ComputedFieldType::GenderAggregateLabel => vec![
-                            "女性".to_string(),
-                            "男性".to_string(),
+                            "女性".into(),
+                            "男性".into(),
                         ],
                         ComputedFieldType::MaritalStatusAggregateLabel => vec![
-                            "既婚".to_string(),
-                            "未婚".to_string(),
+                            "既婚".into(),
+                            "未婚".into(),
                         ],
                         ComputedFieldType::ChildrenAggregateLabel => vec![
-                            "0人".to_string(),
-                            "1人".to_string(),
-                            "2人".to_string(),
-                            "3人".to_string(),
-                            "4人以上".to_string(),
+                            "0人".into(),
+                            "1人".into(),
+                            "2人".into(),
+                            "3人".into(),
+                            "4人以上".into(),
                         ],
-                        ComputedFieldType::JobAggregateLabel => Job::get_all_string(lng),
-                        ComputedFieldType::RegionAggregateLabel => Region::get_all_string(lng),
+                        ComputedFieldType::JobAggregateLabel => Job::get_all_string(lng).into_iter()
+                            .map(CellValue::from).collect(),
+                        ComputedFieldType::RegionAggregateLabel => Region::get_all_string(lng).into_iter()
+                            .map(CellValue::from).collect(),
                         ComputedFieldType::YearlyIncomeAggregateLabel => vec![
-                            "100万円未満".to_string(),
-                            "100～200万円未満".to_string(),
-                            "200～300万円未満".to_string(),
-                            "300～400万円未満".to_string(),
-                            "400～500万円未満".to_string(),
-                            "500～600万円未満".to_string(),
-                            "600～700万円未満".to_string(),
-                            "700～800万円未満".to_string(),
-                            "800～900万円未満".to_string(),
-                            "900～1000万円未満".to_string(),
-                            "1000～1200万円未満".to_string(),
-                            "1200～1500万円未満".to_string(),
-                            "1500～2000万円未満".to_string(),
-                            "2000万円以上".to_string(),
+                            "100万円未満".into(),
+                            "100～200万円未満".into(),
+                            "200～300万円未満".into(),
+                            "300～400万円未満".into(),
+                            "400～500万円未満".into(),
+                            "500～600万円未満".into(),
+                            "600～700万円未満".into(),
+                            "700～800万円未満".into(),
+                            "800～900万円未満".into(),
+                            "900～1000万円未満".into(),
+                            "1000～1200万円未満".into(),
+                            "1200～1500万円未満".into(),
+                            "1500～2000万円未満".into(),
+                            "2000万円以上".into(),
                         ],
 
                         // Values
@@ -83,8 +86,8 @@ impl Data {
                                     60..= u8::MAX => arr[5] += 1,
                                 }
                             }
-                            arr.to_vec().into_iter().map(|x| x.to_string())
-                               .collect::<Vec<String>>()
+                            arr.to_vec().into_iter().map(CellValue::from)
+                               .collect::<Vec<CellValue>>()
                         },
                         ComputedFieldType::AgeGroup1070AggregateValue => {
                             let mut arr: [usize; 7] = [0; 7];
@@ -99,8 +102,8 @@ impl Data {
                                     70..=u8::MAX => arr[6] += 1,
                                 }
                             }
-                            arr.to_vec().into_iter().map(|x| x.to_string())
-                               .collect::<Vec<String>>()
+                            arr.to_vec().into_iter().map(CellValue::from)
+                               .collect::<Vec<CellValue>>()
                         },
                         ComputedFieldType::GenderAggregateValue => {
                             let mut arr: [usize; 2] = [0; 2];
@@ -110,7 +113,7 @@ impl Data {
                                     Gender::Male => arr[1] += 1
                                 }
                             }
-                            arr.to_vec().into_iter().map(|x| x.to_string()).collect()
+                            arr.to_vec().into_iter().map(CellValue::from).collect()
                         },
                         ComputedFieldType::MaritalStatusAggregateValue => {
                             let mut arr: [usize; 2] = [0; 2];
@@ -120,7 +123,7 @@ impl Data {
                                     MaritalStatus::Single => arr[1] += 1,
                                 }
                             }
-                            arr.to_vec().into_iter().map(|x| x.to_string()).collect()
+                            arr.to_vec().into_iter().map(CellValue::from).collect()
                         },
                         ComputedFieldType::ChildrenAggregateValue => {
                             let mut arr: [usize; 5] = [0; 5];
@@ -133,7 +136,7 @@ impl Data {
                                     4..=u16::MAX => arr[4] += 1,
                                 }
                             }
-                            arr.to_vec().into_iter().map(|x| x.to_string()).collect()
+                            arr.to_vec().into_iter().map(CellValue::from).collect()
                         },
                         ComputedFieldType::JobAggregateValue => {
                             let jobs = Job::get_all();
@@ -146,7 +149,7 @@ impl Data {
                                     }
                                 }
                             }
-                            vec.into_iter().map(|x| x.to_string()).collect()
+                            vec.into_iter().map(CellValue::from).collect()
                         },
                         ComputedFieldType::RegionAggregateValue => {
                             let regions = Region::get_all();
@@ -159,7 +162,7 @@ impl Data {
                                     }
                                 }
                             }
-                            vec.into_iter().map(|x| x.to_string()).collect()
+                            vec.into_iter().map(CellValue::from).collect()
                         },
                         ComputedFieldType::YearlyIncomeAggregateValue => {
                             let mut arr: [usize; 14] = [0; 14];
@@ -181,11 +184,11 @@ impl Data {
                                     (_, _ ) => arr[13] += 1,
                                 }
                             }
-                            arr.to_vec().into_iter().map(|x| x.to_string()).collect()
+                            arr.to_vec().into_iter().map(CellValue::from).collect()
                         },
 
                         // Others
-                        _ => Vec::<String>::new()
+                        _ => Vec::<CellValue>::new()
                     }
                 },
                 _ => panic!("Called get_computed_field_labels on non-computed field.")
@@ -285,6 +288,7 @@ impl Data {
             .collect::<Vec<f64>>();
         // if *field == FieldType::Custom(5) { println!("      -- step187"); }
         freq_perc.perc = perc;
+        freq_perc.total = cnt;
         // if *field == FieldType::Custom(5) { println!("      -- step188"); }
         Ok(freq_perc)
     }
@@ -427,7 +431,7 @@ impl Data {
                                   meta: &Meta,
                                   created_year: u16,
                                   lng: Language)
-        -> Result<Vec<String>, RustlyzerError>
+        -> Result<Vec<f64>, RustlyzerError>
     {
         let cnt_dist = self.get_count_distribution(
             field_base,
@@ -440,8 +444,8 @@ impl Data {
         let total = cnt_dist.iter().sum::<usize>() as f64;
         Ok(cnt_dist
             .into_iter()
-            .map(|v| format!("{:.2}", (v as f64 / total) * 100f64))
-            .collect::<Vec<String>>())
+            .map(|v| (v as f64 / total) * 100f64)
+            .collect::<Vec<f64>>())
     }
 
     fn increment_field_freq_in_map(
@@ -491,7 +495,7 @@ impl Data {
     }
 
 
-    pub fn get_static_computed_field_footer(&self, field: &FieldType) -> String {
+    pub fn get_static_computed_field_footer(&self, field: &FieldType) -> CellValue {
        match field {
            FieldType::Computed(computed) => match computed {
                // Total for static field values
@@ -502,7 +506,7 @@ impl Data {
                ComputedFieldType::ChildrenAggregateValue |
                ComputedFieldType::JobAggregateValue |
                ComputedFieldType::RegionAggregateValue |
-               ComputedFieldType::YearlyIncomeAggregateValue => self.records.len().to_string(),
+               ComputedFieldType::YearlyIncomeAggregateValue => self.records.len().into(),
 
                // Total string
                ComputedFieldType::AgeGroup1060AggregateLabel |
@@ -512,9 +516,9 @@ impl Data {
                ComputedFieldType::ChildrenAggregateLabel |
                ComputedFieldType::JobAggregateLabel |
                ComputedFieldType::RegionAggregateLabel |
-               ComputedFieldType::YearlyIncomeAggregateLabel => "計".to_string(),
+               ComputedFieldType::YearlyIncomeAggregateLabel => "計".into(),
 
-               _ => "".to_string(),
+               _ => CellValue::Empty,
            },
            _ => panic!("Called get_computed_field_total on non-computed field.")
        }
@@ -587,10 +591,12 @@ impl Data {
 pub struct FreqPerc {
     pub freq: Vec<usize>,
     pub perc: Vec<f64>,
+    /// Number of records with a value, the base of `perc`
+    pub total: usize,
 }
 
 impl FreqPerc {
     fn new() -> Self {
-        FreqPerc{freq: Vec::<usize>::new(), perc: Vec::<f64>::new()}
+        FreqPerc{freq: Vec::<usize>::new(), perc: Vec::<f64>::new(), total: 0}
     }
 }
\ No newline at end of file
@@ -0,0 +1,234 @@
+use super::columns::ColumnMapping;
+use super::xlsx_input::InputSheet;
+use crate::errors::RustlyzerError;
+use encoding_rs::{Decoder, DecoderResult, Encoding, SHIFT_JIS, UTF_8};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read};
+use std::str::FromStr;
+
+/// Character encoding of csv input.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InputEncoding {
+    /// UTF-8 if the input starts with a UTF-8 BOM or valid UTF-8, Shift_JIS otherwise
+    Auto,
+    #[serde(alias = "utf-8")]
+    Utf8,
+    /// Shift_JIS with the Windows (CP932) extensions, as exported by Japanese Excel
+    #[serde(alias = "shift-jis", alias = "sjis", alias = "cp932", alias = "windows-31j")]
+    ShiftJis,
+}
+
+impl Default for InputEncoding {
+    fn default() -> Self {
+        InputEncoding::Auto
+    }
+}
+
+impl FromStr for InputEncoding {
+    type Err = RustlyzerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(InputEncoding::Auto),
+            "utf8" | "utf-8" => Ok(InputEncoding::Utf8),
+            "shift_jis" | "shift-jis" | "sjis" | "cp932" | "windows-31j" => {
+                Ok(InputEncoding::ShiftJis)
+            },
+            _ => Err(RustlyzerError::InvalidConfigValError {
+                config_item: "encoding".to_string(),
+                val: s.to_string(),
+                expected_values: vec!["auto".to_string(), "utf8".to_string(),
+                                      "shift_jis".to_string()],
+            }),
+        }
+    }
+}
+
+/// Dialect of csv input.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CsvOptions {
+    pub encoding: InputEncoding,
+    /// Drop a UTF-8 byte order mark at the start of the input
+    pub strip_bom: bool,
+    /// Field separator, an ascii character
+    pub delimiter: char,
+    /// Quote character, an ascii character
+    pub quote: char,
+    /// Lines before the header row, skipped
+    pub header_offset: usize,
+    /// Header of each input column
+    pub columns: ColumnMapping,
+    /// Skip rows that fail to parse or validate, recording them, instead of failing the read
+    pub lenient: bool,
+    /// Worksheet read from xlsx input
+    pub sheet: InputSheet,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            encoding: InputEncoding::Auto,
+            strip_bom: true,
+            delimiter: ',',
+            quote: '"',
+            header_offset: 0,
+            columns: ColumnMapping::default(),
+            lenient: false,
+            sheet: InputSheet::default(),
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Delimiter and quote as bytes, for the csv reader.
+    pub(crate) fn delimiter_and_quote(&self) -> Result<(u8, u8), RustlyzerError> {
+        let byte = |name: &str, c: char| if c.is_ascii() {
+            Ok(c as u8)
+        } else {
+            Err(RustlyzerError::InvalidConfigItemError(
+                format!("csv {} {:?} is not an ascii character", name, c)))
+        };
+        Ok((byte("delimiter", self.delimiter)?, byte("quote", self.quote)?))
+    }
+}
+
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+/// Bytes read before picking the encoding of `InputEncoding::Auto`
+const SNIFF_LEN: usize = 64 * 1024;
+const CHUNK_LEN: usize = 8 * 1024;
+
+/// Reader handing out csv input as UTF-8, decoded and with the lines before the header dropped.
+///
+/// Bytes invalid in the encoding fail the read with `io::ErrorKind::InvalidData`, naming the
+/// line of the input they are on.
+pub(crate) struct DecodingReader<R> {
+    inner: R,
+    encoding: InputEncoding,
+    strip_bom: bool,
+    /// Set once the start of the input was read
+    decoder: Option<Decoder>,
+    /// Input read but not decoded yet
+    pending: Vec<u8>,
+    eof: bool,
+    /// Decoded output not handed out yet, from `output_pos`
+    output: Vec<u8>,
+    output_pos: usize,
+    /// Line breaks decoded so far
+    lines: usize,
+    lines_to_skip: usize,
+}
+
+impl<R: Read> DecodingReader<R> {
+    pub(crate) fn new(inner: R, options: &CsvOptions) -> Self {
+        DecodingReader {
+            inner,
+            encoding: options.encoding,
+            strip_bom: options.strip_bom,
+            decoder: None,
+            pending: Vec::<u8>::new(),
+            eof: false,
+            output: Vec::<u8>::new(),
+            output_pos: 0,
+            lines: 0,
+            lines_to_skip: options.header_offset,
+        }
+    }
+
+    /// Read more input into `pending`, noting the end of the input.
+    fn fill(&mut self, len: usize) -> io::Result<()> {
+        let start = self.pending.len();
+        self.pending.resize(start + len, 0);
+        let n = loop {
+            match self.inner.read(&mut self.pending[start..]) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.pending.truncate(start);
+                    return Err(e);
+                },
+            }
+        };
+        self.pending.truncate(start + n);
+        self.eof = n == 0;
+        Ok(())
+    }
+
+    /// Read the start of the input and pick its encoding.
+    fn start(&mut self) -> io::Result<Decoder> {
+        while !self.eof && self.pending.len() < SNIFF_LEN {
+            self.fill(CHUNK_LEN)?;
+        }
+        let has_bom = self.pending.starts_with(UTF8_BOM);
+        if has_bom && self.strip_bom {
+            self.pending.drain(..UTF8_BOM.len());
+        }
+        let encoding: &'static Encoding = match self.encoding {
+            InputEncoding::Utf8 => UTF_8,
+            InputEncoding::ShiftJis => SHIFT_JIS,
+            InputEncoding::Auto if has_bom => UTF_8,
+            // A multi-byte character cut at the end of the sample is still UTF-8
+            InputEncoding::Auto => match std::str::from_utf8(&self.pending) {
+                Ok(_) => UTF_8,
+                Err(e) if e.error_len().is_none() => UTF_8,
+                Err(_) => SHIFT_JIS,
+            },
+        };
+        Ok(encoding.new_decoder_without_bom_handling())
+    }
+
+    /// Decode the pending input into `output`.
+    fn decode(&mut self) -> io::Result<()> {
+        let mut decoder = match self.decoder.take() {
+            Some(decoder) => decoder,
+            None => self.start()?,
+        };
+        if !self.eof && self.pending.len() < CHUNK_LEN {
+            self.fill(CHUNK_LEN)?;
+        }
+        let capacity = decoder.max_utf8_buffer_length_without_replacement(self.pending.len())
+            .unwrap_or(self.pending.len() * 3 + 16);
+        self.output.clear();
+        self.output.resize(capacity, 0);
+        self.output_pos = 0;
+        let (result, read, written) = decoder.decode_to_utf8_without_replacement(
+            &self.pending, &mut self.output, self.eof);
+        self.output.truncate(written);
+        self.lines += self.output.iter().filter(|&&b| b == b'\n').count();
+        let encoding = decoder.encoding();
+        self.decoder = Some(decoder);
+        if let DecoderResult::Malformed(_, _) = result {
+            // The invalid bytes follow the decoded output
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                "line {}: bytes not valid {}", self.lines + 1, encoding.name())));
+        }
+        self.pending.drain(..read);
+        // Drop the lines before the header
+        while self.lines_to_skip > 0 && self.output_pos < self.output.len() {
+            match self.output[self.output_pos..].iter().position(|&b| b == b'\n') {
+                Some(i) => {
+                    self.output_pos += i + 1;
+                    self.lines_to_skip -= 1;
+                },
+                None => self.output_pos = self.output.len(),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.output_pos == self.output.len() {
+            if self.eof && self.pending.is_empty() && self.decoder.is_some() {
+                return Ok(0);
+            }
+            self.decode()?;
+        }
+        let n = buf.len().min(self.output.len() - self.output_pos);
+        buf[..n].copy_from_slice(&self.output[self.output_pos..self.output_pos + n]);
+        self.output_pos += n;
+        Ok(n)
+    }
+}
@@ -1,12 +1,15 @@
 use hashbrown::HashMap;
-use serde_json::Value;
+use serde_json::{Map, Value};
+use crate::ds::table::CellValue;
 use crate::errors::RustlyzerError;
+use chrono::NaiveDateTime;
 use super::{Language, Prefecture, Region, FieldType};
 use super::enums::{PurchaseStatus, YearlyIncomeRange, Job, MaritalStatus, Gender, AgeRange1060,
                    AgeRange1070,
                    ChildrenRange};
 use crate::helpers::{EnumAttrs, Round, ExtractFromStr};
 use serde::{Serialize, Deserialize};
+use csv::StringRecord;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub(crate) struct InputRecord {
@@ -34,7 +37,56 @@ pub(crate) struct InputRecord {
     pub(crate) custom_fields: HashMap<usize, Option<Value>>,
 }
 
+/// Standard fields of `InputRecord`, each read on its own to find the column a record fails to
+/// parse at: neither csv nor json tell the column of a failing enum or flattened field.
+#[derive(Deserialize)]
+struct TypedFields {
+    id: Option<String>,
+    user_id: Option<String>,
+    campaign_id: Option<String>,
+    price: Option<u32>,
+    bonus_point: Option<u32>,
+    status: Option<PurchaseStatus>,
+    created_at: Option<String>,
+    #[serde(rename = "updated at")]
+    updated_at: Option<String>,
+    email: Option<String>,
+    nickname: Option<String>,
+    gender: Option<Gender>,
+    birth_year: Option<u16>,
+    job: Option<Job>,
+    prefecture: Option<Prefecture>,
+    marital_status: Option<MaritalStatus>,
+    children: Option<u16>,
+    household_income_min: Option<u64>,
+    household_income_max: Option<u64>,
+}
+
 impl InputRecord {
+    /// Column and reason of the first value of `record`, under `headers`, that fails to parse.
+    pub(crate) fn locate_parse_error(record: &StringRecord, headers: &StringRecord)
+        -> Option<(usize, String)> {
+        headers.iter().zip(record.iter()).enumerate().find_map(|(i, (header, value))| {
+            StringRecord::from(vec![value])
+                .deserialize::<TypedFields>(Some(&StringRecord::from(vec![header])))
+                .err()
+                .map(|e| match e.kind() {
+                    csv::ErrorKind::Deserialize { err, .. } => (i, err.kind().to_string()),
+                    _ => (i, e.to_string()),
+                })
+        })
+    }
+
+    /// Key and reason of the first value of json `record` that fails to parse.
+    pub(crate) fn locate_value_error(record: &Map<String, Value>) -> Option<(String, String)> {
+        record.iter().find_map(|(key, value)| {
+            let mut single = Map::new();
+            single.insert(key.to_owned(), value.to_owned());
+            TypedFields::deserialize(&Value::Object(single)).err()
+                .map(|e| (key.to_owned(), e.to_string()))
+        })
+    }
+
     /// Returns custom field value for field.
     /// - `field` has to be of field type `FieldType::Custom(_)`
     /// - For `FieldType::Custom(i)`, `i` has to exist in records
@@ -66,6 +118,26 @@ impl InputRecord {
         }
     }
 
+    /// Typed value of `field`, as written to the raw data tables.
+    pub fn get_field_value(
+        &self,
+        field: &FieldType,
+        lng: Language,
+        created_year: u16,
+    ) -> Result<CellValue, RustlyzerError> {
+        match field {
+            FieldType::Custom(_) => match self.get_custom_field(field)? {
+                Some(val) => Ok(self.get_value_inner(val)),
+                None => Ok(CellValue::Empty),
+            },
+            FieldType::Age => Ok(CellValue::Integer(self.get_age(created_year) as i64)),
+            FieldType::CreatedAt => Ok(parse_datetime(&self.created_at)
+                .map(CellValue::Date)
+                .unwrap_or_else(|| CellValue::Text(self.created_at.clone()))),
+            _ => Ok(CellValue::Text(self.get_static_field_str(field, lng, created_year))),
+        }
+    }
+
     pub fn validate_birth_year(&self, created_year: u16, row: usize) -> Result<(), RustlyzerError>{
         let age = (created_year as i16 - self.birth_year as i16);
         if age < 0 {
@@ -119,6 +191,18 @@ impl InputRecord {
         }
     }
 
+    fn get_value_inner(&self, val: &Value) -> CellValue {
+        match val {
+            Value::String(val) => CellValue::Text(val.to_owned()),
+            Value::Number(val) => match val.as_i64() {
+                Some(n) => CellValue::Integer(n),
+                None => CellValue::Float(val.as_f64().unwrap_or(f64::NAN)),
+            },
+            Value::Null => CellValue::Empty,
+            _ => CellValue::Text(self.get_value_inner_str(val)),
+        }
+    }
+
     fn get_value_inner_str(&self, val: &Value) -> String {
         match val {
             Value::String(val) => val.to_owned(),
@@ -299,6 +383,13 @@ impl InputRecord {
 }
 
 // Custom deserialization
+/// Parse a timestamp as exported by the survey platform (`2020年7月3日 19:01`) or in iso style.
+fn parse_datetime(s: &str) -> Option<NaiveDateTime> {
+    ["%Y年%m月%d日 %H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y/%m/%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(s.trim(), format).ok())
+}
+
 fn custom_fields_de<'de, D>(de: D) -> Result<HashMap<usize, Option<Value>>, D::Error>
 where
     D: serde::Deserializer<'de>,
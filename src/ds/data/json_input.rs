@@ -0,0 +1,203 @@
+use super::csv_input::CsvOptions;
+use super::input_record::InputRecord;
+use super::{Data, InputFormat};
+use crate::ds::data_quality::{DataQuality, RowError};
+use crate::errors::RustlyzerError;
+use hashbrown::HashMap;
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+
+/// Json records of the input turned into kept records, as `Data::from_reader_filtered` turns
+/// csv rows.
+pub(super) struct JsonRecords<'o, F> {
+    options: &'o CsvOptions,
+    filter: F,
+    /// Key `InputRecord` reads each input key seen so far as
+    keys: HashMap<String, String>,
+    records: Vec<InputRecord>,
+    read: usize,
+    quality: DataQuality,
+    /// Error that stopped the array being read
+    error: Option<RustlyzerError>,
+}
+
+impl<'o, F> JsonRecords<'o, F>
+where F: FnMut(usize, InputRecord) -> Result<Option<InputRecord>, RustlyzerError> {
+    pub(super) fn new(options: &'o CsvOptions, filter: F) -> Self {
+        JsonRecords {
+            options,
+            filter,
+            keys: HashMap::<String, String>::new(),
+            records: Vec::<InputRecord>::new(),
+            read: 0,
+            // Rejected rows are written with the whole record as json
+            quality: DataQuality { headers: vec!["record".to_string()], ..Default::default() },
+            error: None,
+        }
+    }
+
+    /// Read the record `value`, at `row` of the input.
+    pub(super) fn push(&mut self, row: usize, value: Value) -> Result<(), RustlyzerError> {
+        let index = self.read;
+        self.read += 1;
+        let object = match value {
+            Value::Object(object) => object,
+            value => return self.reject(row, None, None, "not an object".to_string(),
+                                        value.to_string(), None),
+        };
+        let mut record = Map::<String, Value>::new();
+        for (key, value) in object.iter() {
+            if !self.keys.contains_key(key) {
+                let input_key = self.options.columns.input_key(key);
+                self.keys.insert(key.to_owned(), input_key);
+            }
+            match self.keys.get(key) {
+                Some(input_key) if !input_key.is_empty() => {
+                    record.insert(input_key.to_owned(), value.to_owned());
+                },
+                _ => (),
+            }
+        }
+        let parsed = match InputRecord::deserialize(&Value::Object(record.clone())) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                let (column, value, reason) = match InputRecord::locate_value_error(&record) {
+                    Some((key, reason)) => {
+                        (Some(self.source_key(&key)), record.get(&key).cloned(), reason)
+                    },
+                    None => (None, None, e.to_string()),
+                };
+                return self.reject(row, column, value, reason,
+                                   Value::Object(object).to_string(), None);
+            },
+        };
+        match (self.filter)(index, parsed) {
+            Ok(Some(parsed)) => self.records.push(parsed),
+            Ok(None) => (),
+            Err(RustlyzerError::InvalidDataError { field, val, row: err_row }) => {
+                let column = Some(self.source_key(&field));
+                let reason = format!("invalid {}", field);
+                let err = RustlyzerError::InvalidDataError { field, val: val.clone(),
+                    row: err_row };
+                return self.reject(row, column, Some(Value::String(val)), reason,
+                                   Value::Object(object).to_string(), Some(err));
+            },
+            Err(e) => return Err(e),
+        }
+        Ok(())
+    }
+
+    /// Input key read as `input_key`.
+    fn source_key(&self, input_key: &str) -> String {
+        self.keys.iter()
+            .find(|(_, key)| key.as_str() == input_key)
+            .map_or_else(|| input_key.to_string(), |(source, _)| source.to_owned())
+    }
+
+    /// Reject the record `text`, at `row` of the input, in lenient mode. Fail otherwise, with
+    /// `err` or a wrong format error naming the row.
+    fn reject(&mut self, row: usize, column: Option<String>, value: Option<Value>, reason: String,
+              text: String, err: Option<RustlyzerError>) -> Result<(), RustlyzerError> {
+        if !self.options.lenient {
+            let at = column.map_or_else(String::new, |column| format!(" `{}`", column));
+            return Err(err.unwrap_or_else(|| RustlyzerError::JsonInputWrongFormat(
+                format!("record {}{}: {}", row, at, reason))));
+        }
+        let value = match value {
+            Some(Value::String(s)) => s,
+            Some(value) => value.to_string(),
+            None => String::new(),
+        };
+        self.quality.row_errors.push(RowError {
+            row, column: column.unwrap_or_default(), value, reason, record: vec![text] });
+        Ok(())
+    }
+
+    pub(super) fn finish(mut self) -> (Data, usize, DataQuality) {
+        self.records.shrink_to_fit();
+        (Data { records: self.records }, self.read, self.quality)
+    }
+}
+
+impl<'de, 'o, F> Visitor<'de> for &mut JsonRecords<'o, F>
+where F: FnMut(usize, InputRecord) -> Result<Option<InputRecord>, RustlyzerError> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of records")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let mut row = 0;
+        while let Some(value) = seq.next_element::<Value>()? {
+            row += 1;
+            if let Err(e) = self.push(row, value) {
+                self.error = Some(e);
+                return Err(de::Error::custom("record failed"));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Data {
+    pub fn from_json(content: &str) -> Result<Self, RustlyzerError> {
+        let (data, _, _) = Self::from_json_filtered(content.as_bytes(), InputFormat::Json,
+                                                    &CsvOptions::default(),
+                                                    |_, record| Ok(Some(record)))?;
+        Ok(data)
+    }
+
+    pub fn from_ndjson<R: Read>(input: R) -> Result<Self, RustlyzerError> {
+        let (data, _, _) = Self::from_json_filtered(input, InputFormat::Ndjson,
+                                                    &CsvOptions::default(),
+                                                    |_, record| Ok(Some(record)))?;
+        Ok(data)
+    }
+
+    /// Read json `input`, an array of records or a record per line as `format` says, keeping
+    /// what `filter` returns for each record as `from_reader_filtered` does for csv. Record
+    /// keys are the csv headers, renamed by `options.columns`; the other options are for csv
+    /// only, besides `lenient`.
+    ///
+    /// Rows of rejected records are their line for json lines and their position in the
+    /// array otherwise.
+    pub fn from_json_filtered<R, F>(input: R, format: InputFormat, options: &CsvOptions,
+                                    filter: F) -> Result<(Self, usize, DataQuality), RustlyzerError>
+    where R: Read,
+          F: FnMut(usize, InputRecord) -> Result<Option<InputRecord>, RustlyzerError> {
+        let mut records = JsonRecords::new(options, filter);
+        match format {
+            InputFormat::Json => {
+                let mut de = serde_json::Deserializer::from_reader(BufReader::new(input));
+                let result = de.deserialize_seq(&mut records).and_then(|_| de.end());
+                if let Some(e) = records.error.take() {
+                    return Err(e);
+                }
+                result.map_err(|e| RustlyzerError::JsonInputWrongFormat(e.to_string()))?;
+            },
+            InputFormat::Ndjson => {
+                for (i, line) in BufReader::new(input).lines().enumerate() {
+                    let line = line.map_err(|e| RustlyzerError::JsonInputWrongFormat(
+                        format!("line {}: {}", i + 1, e)))?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    match serde_json::from_str::<Value>(&line) {
+                        Ok(value) => records.push(i + 1, value)?,
+                        Err(e) => {
+                            records.read += 1;
+                            records.reject(i + 1, None, None, e.to_string(), line, None)?;
+                        },
+                    }
+                }
+            },
+            InputFormat::Csv | InputFormat::Xlsx => return Err(RustlyzerError::InternalError(
+                format!("{:?} input read as json", format))),
+        }
+        Ok(records.finish())
+    }
+}
@@ -7,15 +7,56 @@ use chrono::prelude::*;
 use hashbrown::HashMap;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::io::Read;
+use std::path::Path;
 use super::meta::{Meta, CustomFieldVariant};
+use crate::ds::data_quality::{DataQuality, RowError};
 
+pub mod columns;
 pub mod computed;
+pub mod csv_input;
 pub mod input_record;
+pub mod json_input;
+pub mod xlsx_input;
 pub mod enums;
 
+use csv_input::{CsvOptions, DecodingReader};
 use enums::{Prefecture, Region};
 use input_record::InputRecord;
 
+/// Format of the survey responses.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InputFormat {
+    Csv,
+    /// Json array of records
+    Json,
+    /// Json lines, a record per line
+    Ndjson,
+    /// Excel workbook, a record per row of one of its sheets
+    Xlsx,
+}
+
+impl Default for InputFormat {
+    fn default() -> Self {
+        InputFormat::Csv
+    }
+}
+
+impl InputFormat {
+    /// Format of the input at `path`, by extension: `.json`, `.ndjson` or `.jsonl`, `.xlsx` or
+    /// `.xlsm`, csv otherwise.
+    pub fn from_path(path: &Path) -> Self {
+        let ext = path.extension().map(|ext| ext.to_string_lossy().to_lowercase());
+        match ext.as_deref() {
+            Some("json") => InputFormat::Json,
+            Some("ndjson") | Some("jsonl") => InputFormat::Ndjson,
+            Some("xlsx") | Some("xlsm") => InputFormat::Xlsx,
+            _ => InputFormat::Csv,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Data {
     pub(crate) records: Vec<InputRecord>,
@@ -23,17 +64,110 @@ pub struct Data {
 
 impl Data {
     pub fn from_csv(content: &str) -> Result<Self, RustlyzerError> {
+        Self::from_reader(content.as_bytes(), &CsvOptions::default())
+    }
+
+    /// Read every record of csv `input`, in the dialect of `options`.
+    pub fn from_reader<R: Read>(input: R, options: &CsvOptions) -> Result<Self, RustlyzerError> {
+        let (data, _, _) = Self::from_reader_filtered(input, options,
+                                                      |_, record| Ok(Some(record)))?;
+        Ok(data)
+    }
+
+    /// Read csv `input` a record at a time, keeping what `filter` returns for each record and
+    /// its row. Only the kept records are held in memory.
+    ///
+    /// Read with `options.lenient`, rows with a wrong number of fields or a value that does not
+    /// parse, and rows `filter` fails with `InvalidDataError`, are skipped and recorded instead
+    /// of failing the read.
+    ///
+    /// Returns the kept records, the number of records read and the rejected rows.
+    pub fn from_reader_filtered<R, F>(input: R, options: &CsvOptions, mut filter: F)
+        -> Result<(Self, usize, DataQuality), RustlyzerError>
+    where R: Read,
+          F: FnMut(usize, InputRecord) -> Result<Option<InputRecord>, RustlyzerError> {
+        let (delimiter, quote) = options.delimiter_and_quote()?;
         let mut rdr = csv::ReaderBuilder::new()
             .has_headers(true)
-            .delimiter(b',')
+            .delimiter(delimiter)
+            .quote(quote)
             .double_quote(true)
-            .from_reader(content.as_bytes());
+            .from_reader(DecodingReader::new(input, options));
+        // Fails on a missing column or a header row that cannot be read before any record is
+        // read, `deserialize` would drop the error
+        let source_headers = rdr.headers()?.clone();
+        let headers = options.columns.input_headers(&source_headers)?;
+        let mut quality = DataQuality {
+            headers: source_headers.iter().map(|h| h.to_string()).collect(),
+            row_errors: Vec::<RowError>::new(),
+        };
         let mut records: Vec<InputRecord> = Vec::new();
-        for result in rdr.deserialize() {
-            let record: InputRecord = result?;
-            records.push(record);
+        let mut raw = csv::StringRecord::new();
+        let mut read = 0;
+        loop {
+            let row = read;
+            // Rejects the row in lenient mode, fails the read otherwise
+            let mut reject = |column: Option<usize>, value: Option<&str>, reason: String,
+                              raw: &csv::StringRecord, err: RustlyzerError| {
+                if !options.lenient {
+                    return Err(err);
+                }
+                let field = |i: Option<usize>, record: &csv::StringRecord| i
+                    .and_then(|i| record.get(i)).unwrap_or("").to_string();
+                quality.row_errors.push(RowError {
+                    row: row + options.header_offset + 2,
+                    column: field(column, &source_headers),
+                    value: value.map_or_else(|| field(column, raw), |v| v.to_string()),
+                    reason,
+                    record: raw.iter().map(|v| v.to_string()).collect(),
+                });
+                Ok(())
+            };
+            match rdr.read_record(&mut raw) {
+                Ok(false) => break,
+                Ok(true) => read += 1,
+                Err(e) => match e.kind() {
+                    csv::ErrorKind::UnequalLengths { expected_len, len, .. } => {
+                        read += 1;
+                        let reason = format!("{} fields, expected {}", len, expected_len);
+                        reject(None, None, reason, &raw, e.into())?;
+                        continue;
+                    },
+                    _ => return Err(e.into()),
+                },
+            }
+            let record = match raw.deserialize::<InputRecord>(Some(&headers)) {
+                Ok(record) => record,
+                Err(e) => match e.kind() {
+                    csv::ErrorKind::Deserialize { err, .. } => {
+                        let (column, reason) = match err.field() {
+                            Some(i) => (Some(i as usize), err.kind().to_string()),
+                            None => match InputRecord::locate_parse_error(&raw, &headers) {
+                                Some((i, reason)) => (Some(i), reason),
+                                None => (None, err.kind().to_string()),
+                            },
+                        };
+                        reject(column, None, reason, &raw, e.into())?;
+                        continue;
+                    },
+                    _ => return Err(e.into()),
+                },
+            };
+            match filter(row, record) {
+                Ok(Some(record)) => records.push(record),
+                Ok(None) => (),
+                Err(RustlyzerError::InvalidDataError { field, val, row: err_row }) => {
+                    let column = headers.iter().position(|h| h == field);
+                    let reason = format!("invalid {}", field);
+                    let err = RustlyzerError::InvalidDataError { field, val: val.clone(),
+                        row: err_row };
+                    reject(column, Some(&val), reason, &raw, err)?;
+                },
+                Err(e) => return Err(e),
+            }
         }
-        Ok(Data { records })
+        records.shrink_to_fit();
+        Ok((Data { records }, read, quality))
     }
 
     pub fn len(&self) -> usize {
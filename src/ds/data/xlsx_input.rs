@@ -0,0 +1,161 @@
+use super::csv_input::CsvOptions;
+use super::input_record::InputRecord;
+use super::json_input::JsonRecords;
+use super::Data;
+use crate::ds::data_quality::DataQuality;
+use crate::errors::RustlyzerError;
+use calamine::{Data as Cell, Reader, Xlsx};
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+use std::io::{Read, Seek};
+use std::str::FromStr;
+
+/// Worksheet of xlsx input, by name or by position from 0.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum InputSheet {
+    Index(usize),
+    Name(String),
+}
+
+impl Default for InputSheet {
+    fn default() -> Self {
+        InputSheet::Index(0)
+    }
+}
+
+/// A number is a position, anything else a name.
+impl FromStr for InputSheet {
+    type Err = RustlyzerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.parse::<usize>() {
+            Ok(index) => InputSheet::Index(index),
+            Err(_) => InputSheet::Name(s.to_string()),
+        })
+    }
+}
+
+/// Fields of `InputRecord` read as text, whatever the type of their cells
+const TEXT_FIELDS: [&str; 7] = ["id", "user_id", "campaign_id", "created_at", "updated at",
+    "email", "nickname"];
+/// Fields of `InputRecord` read as whole numbers, also from text cells
+const NUMBER_FIELDS: [&str; 6] = ["price", "bonus_point", "birth_year", "children",
+    "household_income_min", "household_income_max"];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum ColumnKind {
+    Text,
+    Number,
+    /// Enums and custom fields, keeping the type of their cells
+    Native,
+}
+
+impl ColumnKind {
+    fn of(input_header: &str) -> Self {
+        if TEXT_FIELDS.contains(&input_header) {
+            ColumnKind::Text
+        } else if NUMBER_FIELDS.contains(&input_header) {
+            ColumnKind::Number
+        } else {
+            ColumnKind::Native
+        }
+    }
+}
+
+/// Json value of `cell` in a column of `kind`.
+///
+/// Numbers and booleans keep their type, whole numbers as integers since a workbook stores every
+/// number as a float. Dates are written as `2020-07-03 19:01:00`, empty cells as empty text,
+/// like an empty csv field.
+fn cell_value(cell: &Cell, kind: ColumnKind) -> Value {
+    let value = match cell {
+        Cell::Int(i) => Value::from(*i),
+        Cell::Float(f) if f.fract() == 0.0 && f.abs() < 1e15 => Value::from(*f as i64),
+        Cell::Float(f) => Number::from_f64(*f).map_or_else(|| Value::String(f.to_string()),
+                                                          Value::Number),
+        Cell::Bool(b) => Value::Bool(*b),
+        Cell::String(s) | Cell::DateTimeIso(s) | Cell::DurationIso(s) => {
+            Value::String(s.to_owned())
+        },
+        Cell::DateTime(dt) => Value::String(match dt.as_datetime() {
+            Some(dt) => dt.format("%Y-%m-%d %H:%M:%S").to_string(),
+            None => dt.as_f64().to_string(),
+        }),
+        Cell::Error(e) => Value::String(e.to_string()),
+        Cell::Empty => Value::String(String::new()),
+    };
+    match (kind, value) {
+        (ColumnKind::Text, Value::Number(n)) => Value::String(n.to_string()),
+        (ColumnKind::Text, Value::Bool(b)) => Value::String(b.to_string()),
+        (ColumnKind::Number, Value::String(s)) => match s.trim().parse::<u64>() {
+            Ok(n) => Value::from(n),
+            Err(_) => Value::String(s),
+        },
+        (_, value) => value,
+    }
+}
+
+fn wrong_format(e: calamine::XlsxError) -> RustlyzerError {
+    RustlyzerError::XlsxInputWrongFormat(e.to_string())
+}
+
+impl Data {
+    /// Read the worksheet `options.sheet` of xlsx `input`, a record per row, keeping what `filter`
+    /// returns for each record as `from_reader_filtered` does for csv.
+    ///
+    /// The header row is the first row of the sheet in use, after `options.header_offset` rows,
+    /// and headers are mapped by `options.columns` as csv headers are. Blank rows are skipped.
+    /// Rows of rejected records are the rows of the sheet.
+    pub fn from_xlsx_filtered<R, F>(input: R, options: &CsvOptions, filter: F)
+        -> Result<(Self, usize, DataQuality), RustlyzerError>
+    where R: Read + Seek,
+          F: FnMut(usize, InputRecord) -> Result<Option<InputRecord>, RustlyzerError> {
+        let mut workbook = Xlsx::new(input).map_err(wrong_format)?;
+        let sheet_names = workbook.sheet_names();
+        let name = match &options.sheet {
+            InputSheet::Name(name) => sheet_names.iter().find(|sheet| *sheet == name),
+            InputSheet::Index(index) => sheet_names.get(*index),
+        };
+        let name = name.cloned().ok_or_else(|| RustlyzerError::InvalidConfigValError {
+            config_item: "sheet".to_string(),
+            val: match &options.sheet {
+                InputSheet::Name(name) => name.to_owned(),
+                InputSheet::Index(index) => index.to_string(),
+            },
+            expected_values: sheet_names.to_owned(),
+        })?;
+        let range = workbook.worksheet_range(&name).map_err(wrong_format)?;
+        let first_row = range.start().map_or(0, |(row, _)| row as usize);
+
+        let mut rows = range.rows().enumerate().skip(options.header_offset);
+        let headers = match rows.next() {
+            Some((_, row)) => row.iter()
+                .map(|cell| match cell_value(cell, ColumnKind::Text) {
+                    Value::String(s) => s,
+                    value => value.to_string(),
+                })
+                .collect::<Vec<String>>(),
+            None => return Err(RustlyzerError::XlsxInputWrongFormat(
+                format!("sheet `{}` has no header row", name))),
+        };
+        let kinds = options.columns.input_headers(&StringRecord::from(headers.clone()))?
+            .iter()
+            .map(ColumnKind::of)
+            .collect::<Vec<ColumnKind>>();
+
+        let mut records = JsonRecords::new(options, filter);
+        for (i, row) in rows {
+            if row.iter().all(|cell| *cell == Cell::Empty) {
+                continue;
+            }
+            let record = headers.iter().zip(kinds.iter()).zip(row.iter())
+                .filter(|((header, _), _)| !header.is_empty())
+                .map(|((header, &kind), cell)| (header.to_owned(), cell_value(cell, kind)))
+                .collect::<Map<String, Value>>();
+            records.push(first_row + i + 1, Value::Object(record))?;
+        }
+        Ok(records.finish())
+    }
+}
@@ -0,0 +1,92 @@
+use super::table::{CellValue, Column, Header, Table};
+use super::DataSet;
+use crate::errors::RustlyzerError;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+/// Input row left out of a dataset read leniently, with the first problem found in it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RowError {
+    /// Row of csv input as a spreadsheet numbers it, the header row being row 1. For json input
+    /// the line of json lines and the position in the array of a json array, from 1.
+    pub row: usize,
+    /// Input header of the column at fault, empty if not known
+    pub column: String,
+    /// Value at fault, empty if not known
+    pub value: String,
+    pub reason: String,
+    /// Every value of the row, as read
+    pub record: Vec<String>,
+}
+
+/// Rows rejected while reading the input of a dataset.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct DataQuality {
+    /// Header row of the input, as read. Json input has the single column `record`.
+    pub headers: Vec<String>,
+    /// Rejected rows, in input order. Always empty unless read leniently.
+    pub row_errors: Vec<RowError>,
+}
+
+impl DataQuality {
+    /// Write the rejected rows as csv: the row number, column and reason of each row followed by
+    /// its values under the input headers.
+    pub fn write_csv<W: Write>(&self, output: W) -> Result<(), RustlyzerError> {
+        let mut wtr = csv::WriterBuilder::new().flexible(true).from_writer(output);
+        let mut header = vec!["row", "column", "value", "reason"];
+        header.extend(self.headers.iter().map(|h| h.as_str()));
+        wtr.write_record(&header)?;
+        for error in self.row_errors.iter() {
+            let row = error.row.to_string();
+            let mut record = vec![row.as_str(), &error.column, &error.value, &error.reason];
+            record.extend(error.record.iter().map(|v| v.as_str()));
+            wtr.write_record(&record)?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+impl DataSet {
+    /// Tables of the data-quality sheet: rejected rows per input column, then every rejected
+    /// row with the value and reason it was rejected for.
+    pub fn get_data_quality_tables(&self) -> (Table, Table) {
+        let row_errors = &self.data_quality.row_errors;
+        let text = |s: &str| CellValue::Text(s.to_string());
+
+        // Columns in the order of the input, unknown columns last
+        let mut counts = Vec::<(&str, usize)>::new();
+        for error in row_errors.iter() {
+            match counts.iter_mut().find(|(column, _)| *column == error.column) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((&error.column, 1)),
+            }
+        }
+        let position = |column: &str| self.data_quality.headers.iter().position(|h| h == column)
+            .unwrap_or(usize::MAX);
+        counts.sort_by_key(|&(column, _)| position(column));
+        let mut columns = Column::new(Header::new("列".to_string(), false), Some(text("合計")),
+                                      counts.len());
+        let mut rejected = Column::new(Header::new("除外件数".to_string(), false),
+                                       Some(CellValue::Integer(row_errors.len() as i64)),
+                                       counts.len());
+        for (column, count) in counts.into_iter() {
+            columns.contents.push(if column.is_empty() { text("不明") } else { text(column) });
+            rejected.contents.push(CellValue::Integer(count as i64));
+        }
+        let summary = Table::new(vec![columns, rejected]);
+
+        let titles = ["行", "列", "値", "理由"];
+        let mut cols = titles.iter()
+            .map(|&title| Column::new(Header::new(title.to_string(), false), None,
+                                      row_errors.len()))
+            .collect::<Vec<Column>>();
+        for error in row_errors.iter() {
+            cols[0].contents.push(CellValue::Integer(error.row as i64));
+            cols[1].contents.push(text(&error.column));
+            cols[2].contents.push(text(&error.value));
+            cols[3].contents.push(text(&error.reason));
+        }
+        (summary, Table::new(cols))
+    }
+}
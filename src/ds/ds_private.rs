@@ -1,6 +1,6 @@
 use serde_json::Value;
 use super::field::{FieldType, ComputedFieldType};
-use super::table::{Column, Header, Table};
+use super::table::{CellRef, CellValue, Column, Formula, Header, Table, Total};
 use super::DataSet;
 use super::meta::CustomFieldVariant;
 use crate::errors::RustlyzerError;
@@ -23,11 +23,13 @@ impl DataSet {
         }
         // Display
         col_vec.get_mut(2).ok_or(RustlyzerError::NoneError)?.header.highlight = false;
-        let mut display_contents = Vec::<String>::new();
+        let mut display_contents = Vec::<CellValue>::new();
         for val in col_vec.get(1).ok_or(RustlyzerError::NoneError)?.contents.iter() {
-            display_contents.push(format!("{}件", val));
+            display_contents.push(format!("{}件", val).into());
         }
         col_vec.get_mut(2).ok_or(RustlyzerError::NoneError)?.contents = display_contents;
+        col_vec.get_mut(1).ok_or(RustlyzerError::NoneError)?.footer_formula =
+            Some(Formula::ColumnSum);
         Ok(Table::new(col_vec))
     }
 
@@ -50,17 +52,29 @@ impl DataSet {
         col_vec.get_mut(2).ok_or(RustlyzerError::NoneError)?.header.highlight = false;
         col_vec.get_mut(3).ok_or(RustlyzerError::NoneError)?.header.highlight = false;
         let total: f64 = self.data.records.len() as f64;
-        let mut graph_display_contents = Vec::<String>::new();
-        let mut percentage_contents = Vec::<String>::new();
+        let mut graph_display_contents = Vec::<CellValue>::new();
+        let mut percentage_contents = Vec::<CellValue>::new();
         for (i, val) in col_vec.get(1).ok_or(RustlyzerError::NoneError)?.contents.iter().enumerate() {
-            let num_val = val.parse::<usize>()?;
+            let num_val = match val {
+                CellValue::Integer(n) => *n,
+                _ => return Err(RustlyzerError::ParseIntError),
+            };
             graph_display_contents.push(format!("{}(n={})", col_vec.get(0).ok_or(RustlyzerError::NoneError)?.contents.get(i)
-               .ok_or(RustlyzerError::NoneError)?, num_val));
-            percentage_contents.push(format!("{:.1}%", (num_val as f64/ total) * 100.0));
+               .ok_or(RustlyzerError::NoneError)?, num_val).into());
+            percentage_contents.push(CellValue::Percentage((num_val as f64/ total) * 100.0));
         }
+        let percentage_formulas = (0..percentage_contents.len())
+            .map(|row| Some(Formula::Share { count: CellRef::Table { col: 1, row },
+                total: Total::Footer(1) }))
+            .collect::<Vec<Option<Formula>>>();
         col_vec.get_mut(2).ok_or(RustlyzerError::NoneError)?.contents = graph_display_contents;
         col_vec.get_mut(3).ok_or(RustlyzerError::NoneError)?.contents = percentage_contents;
-        col_vec.get_mut(3).ok_or(RustlyzerError::NoneError)?.footer = Some("100.0%".to_string());
+        col_vec.get_mut(3).ok_or(RustlyzerError::NoneError)?.formulas = percentage_formulas;
+        col_vec.get_mut(3).ok_or(RustlyzerError::NoneError)?.footer = Some(CellValue::Percentage(100.0));
+        col_vec.get_mut(1).ok_or(RustlyzerError::NoneError)?.footer_formula =
+            Some(Formula::ColumnSum);
+        col_vec.get_mut(3).ok_or(RustlyzerError::NoneError)?.footer_formula =
+            Some(Formula::ColumnSum);
 
 
         Ok(Table::new(col_vec))
@@ -113,7 +127,7 @@ impl DataSet {
             // let mut base_col = cols.get_mut(0).unwrap(); // always succeed
             let mut base_col = self.init_col_for_field(field)?;
             for record in self.data.records.iter() {
-                base_col.contents.push(record.get_field_value_as_str(
+                base_col.contents.push(record.get_field_value(
                     field,
                     self.config.lng,
                     self.config.created_year,
@@ -134,12 +148,12 @@ impl DataSet {
                                 }
                                 false
                             }) {
-                                col.contents.push(String::from("1"));
+                                col.contents.push(CellValue::Integer(1));
                             } else {
-                                col.contents.push(String::from("0"));
+                                col.contents.push(CellValue::Integer(0));
                             }
                         }
-                        _ => col.contents.push(String::from("0")),
+                        _ => col.contents.push(CellValue::Integer(0)),
                     }
                 }
                 cols.push(col);
@@ -150,7 +164,7 @@ impl DataSet {
         } else {
             let mut col = self.init_col_for_field(field)?;
             for record in self.data.records.iter() {
-                match record.get_field_value_as_str(
+                match record.get_field_value(
                     field,
                     self.config.lng,
                     self.config.created_year,
@@ -247,19 +261,19 @@ impl DataSet {
     pub(super) fn get_crosstab_primary_base_cols(&self, field: &FieldType) -> Result<Vec<Column>,
         RustlyzerError> {
         let header = Header::new(self.get_crosstab_field_title(field)?, true);
-        let contents = Vec::<String>::new();
+        let contents = Vec::<CellValue>::new();
         let col_1 = Column::from_contents(header, contents, None);
 
         let header = Header::new(self.get_crosstab_field_type(field)?, true);
-        let contents = Vec::<String>::new();
+        let contents = Vec::<CellValue>::new();
         let col_2 = Column::from_contents(header, contents, None);
 
         let header = Header::new(self.get_crosstab_field_label(field)?, true);
-        let contents = Vec::<String>::new();
+        let contents = Vec::<CellValue>::new();
         let col_3 = Column::from_contents(header, contents, None);
 
         let header = Header::new("選択肢".to_string(), true);
-        let contents = vec!["件数".to_string(), "割合".to_string()];
+        let contents = vec!["件数".into(), "割合".into()];
         let col_4 = Column::from_contents(header, contents, None);
         Ok(vec![col_1, col_2, col_3, col_4])
     }
@@ -269,8 +283,8 @@ impl DataSet {
         let header = Header::new(self.get_crosstab_field_title(field)?, false);
         // if *field == FieldType::Custom(5) { println!("      -- step12"); }
         let contents = vec![
-            self.get_crosstab_field_type(field)?,
-            self.get_crosstab_field_label(field)?
+            self.get_crosstab_field_type(field)?.into(),
+            self.get_crosstab_field_label(field)?.into()
         ];
         // if *field == FieldType::Custom(5) { println!("      -- step13"); }
         let col_1 = Column::from_contents(header, contents, None);
@@ -278,7 +292,8 @@ impl DataSet {
         // if *field == FieldType::Custom(5) { println!("      -- step14"); }
         let header = Header::new("選択肢".to_string(), false);
         // if *field == FieldType::Custom(5) { println!("      -- step15"); }
-        let contents = self.data.get_field_variants_as_string(field, &self.meta, self.config.lng)?;
+        let contents = self.data.get_field_variants_as_string(field, &self.meta, self.config.lng)?
+            .into_iter().map(CellValue::from).collect::<Vec<CellValue>>();
         // if *field == FieldType::Custom(5) { println!("      -- step16"); }
         let len = contents.len();
         // if *field == FieldType::Custom(5) { println!("      -- step17"); }
@@ -295,17 +310,21 @@ impl DataSet {
         // if *field == FieldType::Custom(5) { println!("      -- step19"); }
         let header = Header::new("件数".to_string(), false);
         // if *field == FieldType::Custom(5) { println!("      -- step20"); }
-        let contents = freq_perc.freq.iter().map(|&n| n.to_string()).collect::<Vec<String>>();
+        let contents = freq_perc.freq.iter().map(|&n| n.into()).collect::<Vec<CellValue>>();
         // if *field == FieldType::Custom(5) { println!("      -- step21"); }
         let col_3 = Column::from_contents(header, contents, None);
         // if *field == FieldType::Custom(5) { println!("      -- step22"); }
 
         let header = Header::new("割合".to_string(), false);
         // if *field == FieldType::Custom(5) { println!("      -- step23"); }
-        let contents = freq_perc.perc.iter().map(|&n| format!("{:.2}%", n))
-            .collect::<Vec<String>>();
+        let contents = freq_perc.perc.iter().map(|&n| CellValue::Percentage(n))
+            .collect::<Vec<CellValue>>();
         // if *field == FieldType::Custom(5) { println!("      -- step24"); }
-        let col_4 = Column::from_contents(header, contents, None);
+        let mut col_4 = Column::from_contents(header, contents, None);
+        col_4.formulas = (0..freq_perc.perc.len())
+            .map(|row| Some(Formula::Share { count: CellRef::Table { col: 2, row },
+                total: Total::Constant(freq_perc.total as f64) }))
+            .collect();
         // if *field == FieldType::Custom(5) { println!("      -- step25"); }
 
         Ok(vec![col_1, col_2, col_3, col_4])
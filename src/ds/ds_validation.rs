@@ -6,21 +6,22 @@ use super::data::{input_record::InputRecord, Data};
 use crate::errors::RustlyzerError;
 use serde_json::Value;
 use crate::helpers::strings::into_clean_string;
+use std::collections::HashMap;
 
 impl DataSet {
-    pub(super) fn validate_and_filter(mut data: Data, meta: &Meta, config: &DataSetConfig) ->
-                                                                                      Result<Data,
-    RustlyzerError> {
-       let mut n_records = Vec::<InputRecord>::with_capacity(data.records.len()/2);
-        for (row, mut record) in data.records.into_iter().enumerate() {
+    /// Validate `record`, at `row` of the input, and clean it. Records not matching every
+    /// category of `include_map` (see `IncludeCriteria::map_from_vec`) give `None`.
+    pub(super) fn validate_and_filter(mut record: InputRecord, row: usize, meta: &Meta,
+                                      config: &DataSetConfig,
+                                      include_map: &HashMap<String, Vec<IncludeCriteria>>)
+        -> Result<Option<InputRecord>, RustlyzerError> {
            record.validate_birth_year(config.created_year, row)?;
             let mut would_include = true;
             DataSet::validate_meta_data(&mut record, meta, row)?;
-            let include_map = IncludeCriteria::map_from_vec(&config.includes);
-            for (cat_string, items) in include_map.into_iter() {
+            for (cat_string, items) in include_map.iter() {
                 would_include = false; // Needs to matched and made false to NOT ignore
-                for item in items.into_iter() {
-                    match item {
+                for item in items.iter() {
+                    match *item {
                         IncludeCriteria::PurchaseStatusInclude(cond) => if record.status == cond {
                           would_include = true;
                           break;
@@ -62,16 +63,7 @@ impl DataSet {
             }
             DataSet::clean_data(&mut record);
 
-            if would_include {
-                n_records.push(record);
-            }
-        }
-       data.records = n_records;
-        Ok(data)
-       //  data.records.iter().enumerate().map(|(row,record)| {
-       //      record.validate_birth_year(config.created_year, row).or_else()
-       //  });
-       // Ok(())
+            Ok(if would_include { Some(record) } else { None })
     }
 
     fn validate_meta_data(mut record: &mut InputRecord, meta: &Meta, row_number: usize) -> Result<
@@ -2,41 +2,101 @@ use crate::errors::RustlyzerError;
 use csv::ReaderBuilder;
 use serde_json::Value;
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::path::Path;
 // use anyhow::Result;
 
 mod ds_private;
 mod ds_validation;
 pub mod config;
 pub mod data;
+pub mod data_quality;
 pub mod field;
 pub mod meta;
+pub mod provenance;
 pub mod table;
 
 pub use config::{DataSetConfig, Language};
+pub use data::columns::ColumnMapping;
+pub use data::csv_input::{CsvOptions, InputEncoding};
+pub use data::InputFormat;
+pub use data::xlsx_input::InputSheet;
 use data::{Data, input_record::InputRecord};
+use data_quality::DataQuality;
 use field::FieldType;
 use meta::{CustomFieldVariant, Meta};
-use table::{Column, Header, Table};
+use provenance::{Provenance, SourceFile};
+use table::{CellRef, CellValue, Column, Formula, Header, Table, Total};
 use crate::ds::field::ComputedFieldType;
 use csv::Trim::Fields;
 use crate::ds::table::{TableWithMeta, SpecialCase};
 use crate::ds::config::IncludeCriteria::PurchaseStatusInclude;
 use crate::ds::data::enums::PurchaseStatus;
 use crate::ds::config::IncludeCriteria;
+use crate::helpers::sha256::HashingReader;
 
 pub struct DataSet {
     pub meta: Meta, // fields: HashMap<>
     pub config: DataSetConfig,
     pub data: Data,
+    pub provenance: Provenance,
+    pub data_quality: DataQuality,
 }
 
 impl DataSet {
     pub fn from_data(meta_str: &str, config: DataSetConfig, content_str: &str) -> Result<Self,
         RustlyzerError> {
+        DataSet::from_reader(meta_str, config, content_str.as_bytes(), &CsvOptions::default())
+    }
+
+    /// Load a dataset from csv `input` in the dialect of `options`, validating and filtering each
+    /// record as it is read. Only the kept records are held in memory, not the input.
+    ///
+    /// With `options.lenient` invalid rows are left out and listed in `data_quality`.
+    pub fn from_reader<R: Read>(meta_str: &str, config: DataSetConfig, input: R,
+                                options: &CsvOptions) -> Result<Self, RustlyzerError> {
+        DataSet::from_reader_with_format(meta_str, config, input, InputFormat::Csv, options)
+    }
+
+    /// Load a dataset from `input` in `format`, as `from_reader` does csv. Json records and
+    /// workbook rows are validated and filtered the same way.
+    pub fn from_reader_with_format<R: Read>(meta_str: &str, config: DataSetConfig, input: R,
+                                            format: InputFormat, options: &CsvOptions)
+        -> Result<Self, RustlyzerError> {
         let meta = Meta::from_json(meta_str)?;
-        let mut data = Data::from_csv(content_str)?;
-        let data = DataSet::validate_and_filter(data, &meta, &config)?;
-        Ok(DataSet { meta, config, data })
+        let include_map = IncludeCriteria::map_from_vec(&config.includes);
+        let mut input = HashingReader::new(input);
+        let filter = |row, record|
+            DataSet::validate_and_filter(record, row, &meta, &config, &include_map);
+        let (data, raw_records, data_quality) = match format {
+            InputFormat::Csv => Data::from_reader_filtered(&mut input, options, filter)?,
+            InputFormat::Json | InputFormat::Ndjson => {
+                Data::from_json_filtered(&mut input, format, options, filter)?
+            },
+            // The workbook is a zip archive, read from its end
+            InputFormat::Xlsx => {
+                let mut bytes = Vec::<u8>::new();
+                input.read_to_end(&mut bytes)?;
+                Data::from_xlsx_filtered(Cursor::new(bytes), options, filter)?
+            },
+        };
+        let provenance = Provenance { meta: SourceFile::new(meta_str),
+            input: SourceFile { name: None, sha256: input.finish_hex() }, raw_records };
+        Ok(DataSet { meta, config, data, provenance, data_quality })
+    }
+
+    /// Load a dataset from the meta json at `meta_path` and the input at `input_path`, read as
+    /// `from_reader_with_format` does in the format of its extension (see
+    /// `InputFormat::from_path`) and named after the files.
+    pub fn from_paths(meta_path: &Path, config: DataSetConfig, input_path: &Path,
+                      options: &CsvOptions) -> Result<Self, RustlyzerError> {
+        let mut meta = String::new();
+        File::open(meta_path)?.read_to_string(&mut meta)?;
+        let input = File::open(input_path)?;
+        let format = InputFormat::from_path(input_path);
+        Ok(DataSet::from_reader_with_format(&meta, config, input, format, options)?
+            .with_source_paths(meta_path, input_path))
     }
 
 
@@ -180,6 +240,13 @@ impl DataSet {
             let total = self.data.get_custom_field_total(field, &self.meta) as f64;
             let mut col_percentage = Vec::<f64>::new();
             col_values.iter().for_each(|v| col_percentage.push((*v as f64 / total) * 100.0));
+            let percentage_formulas = (0..col_values.len())
+                .map(|row| Some(Formula::Share { count: CellRef::Table { col: 1, row },
+                    total: Total::Constant(total) }))
+                .collect::<Vec<Option<Formula>>>();
+            let mut percentage_col = Column::from_contents(Header::new("割合".to_string(), false),
+                col_percentage.into_iter().map(CellValue::Percentage).collect(), None);
+            percentage_col.formulas = percentage_formulas;
             let table = TableWithMeta::new(
                 vec![
                     self.meta.get_custom_field_title(field)?,
@@ -187,12 +254,12 @@ impl DataSet {
                     self.meta.get_custom_field_label(field)?
                 ],
                 Table::new(vec![
-                Column::from_contents(Header::new("選択肢".to_string(), false), col_labels, None),
+                Column::from_contents(Header::new("選択肢".to_string(), false), col_labels
+                    .into_iter().map(CellValue::from).collect(), None),
                 Column::from_contents(Header::new("件数".to_string(), false), col_values.into_iter
-                ().map(|val| val.to_string()).collect(),
+                ().map(CellValue::from).collect(),
                                       None),
-                Column::from_contents(Header::new("割合".to_string(), false), col_percentage
-                    .into_iter().map(|val| format!("{:.2}%", val)).collect(), None),
+                percentage_col,
             ]));
             table_with_meta_vec.push(table);
             }
@@ -230,14 +297,15 @@ impl DataSet {
             for (i, variant) in variants.iter().enumerate() {
                let header = Header::new(variant.to_owned(), true);
                 let contents = vec![
-                    freq_perc.freq.get(i).unwrap().to_string(),
-                    format!("{:.2}%", freq_perc.perc.get(i).unwrap())
+                    CellValue::from(*freq_perc.freq.get(i).unwrap()),
+                    CellValue::Percentage(*freq_perc.perc.get(i).unwrap())
                 ];
-                cols_primary.push(Column::from_contents(
-                    header,
-                    contents,
-                    None
-                ));
+                let mut col = Column::from_contents(header, contents, None);
+                col.formulas = vec![None, Some(Formula::Share {
+                    count: CellRef::Table { col: cols_primary.len(), row: 0 },
+                    total: Total::Constant(freq_perc.total as f64),
+                })];
+                cols_primary.push(col);
             }
             tables.push(TableWithMeta::with_special_case(
                 SpecialCase::SpaceAndHighlightOn4,
@@ -256,8 +324,11 @@ impl DataSet {
                 variants.iter().for_each(|v| cols_data.push(
                     Column::new(Header::new(v.to_string(), true), None, variants_secondary.len())
                 ));
+                // Percentages of a row are shares of the row total of the counts table
+                let first_data_col = cols.len();
+                let last_data_col = first_data_col + variants.len() - 1;
                 for variant_secondary in variants_secondary.iter() {
-                    let row: Vec<String>;
+                    let row: Vec<CellValue>;
                     if let CrosstabType::N = crosstab_type {
                         row = self.data.get_count_distribution(
                             field_base,
@@ -266,7 +337,7 @@ impl DataSet {
                             &self.meta,
                             self.config.created_year,
                             self.config.lng
-                        )?.into_iter().map(|v| v.to_string()).collect::<Vec<String>>();
+                        )?.into_iter().map(CellValue::from).collect::<Vec<CellValue>>();
                     } else {
                         row = self.data.get_perc_distribution(
                             field_base,
@@ -275,11 +346,22 @@ impl DataSet {
                             &self.meta,
                             self.config.created_year,
                             self.config.lng
-                        )?;
+                        )?.into_iter().map(CellValue::Percentage).collect::<Vec<CellValue>>();
                     }
                     if row.len() != variants.len() { panic!("Wrong variant length!")}
+                    let row_index = cols_data.get(0).map(|col| col.contents.len()).unwrap_or(0);
                     row.iter().enumerate().for_each(|(i, val)| {
-                        cols_data.get_mut(i).unwrap().contents.push(val.to_owned())
+                        let col = cols_data.get_mut(i).unwrap();
+                        col.contents.push(val.to_owned());
+                        if let CrosstabType::Perc = crosstab_type {
+                            col.formulas.push(Some(Formula::Share {
+                                count: CellRef::Counts { col: first_data_col + i, row: row_index },
+                                total: Total::RowSum {
+                                    first: CellRef::Counts { col: first_data_col, row: row_index },
+                                    last_col: last_data_col,
+                                },
+                            }));
+                        }
                     });
                 }
                 cols.append(&mut cols_data);
@@ -0,0 +1,81 @@
+use super::table::{CellValue, Column, Header, Table};
+use super::DataSet;
+use crate::helpers::sha256::sha256_hex;
+use crate::helpers::EnumAttrs;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Input of a dataset, by file name and content hash.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SourceFile {
+    /// File name, unknown for content loaded from memory
+    pub name: Option<String>,
+    /// SHA-256 of the content, as lowercase hex
+    pub sha256: String,
+}
+
+impl SourceFile {
+    pub fn new(content: &str) -> Self {
+        SourceFile { name: None, sha256: sha256_hex(content.as_bytes()) }
+    }
+}
+
+/// Inputs a dataset was loaded from and how many records passed validation and filtering.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Provenance {
+    pub meta: SourceFile,
+    pub input: SourceFile,
+    /// Records read from the input, before validation and the include criteria
+    pub raw_records: usize,
+}
+
+impl DataSet {
+    /// Name the meta and input files of the dataset after the files at `meta` and `input`.
+    pub fn with_source_paths(mut self, meta: &Path, input: &Path) -> Self {
+        let file_name = |path: &Path| path.file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        self.provenance.meta.name = file_name(meta);
+        self.provenance.input.name = file_name(input);
+        self
+    }
+
+    /// Two column table of the inputs, config and record counts of the dataset, for the report
+    /// generated at `generated_at` by this version of the tool.
+    pub fn get_report_info_table(&self, generated_at: NaiveDateTime) -> Table {
+        let text = |s: &str| CellValue::Text(s.to_string());
+        let source_name = |source: &SourceFile| source.name.as_deref()
+            .map_or(CellValue::Empty, text);
+        let provenance = &self.provenance;
+        let mut rows = vec![
+            ("メタファイル", source_name(&provenance.meta)),
+            ("メタファイル SHA-256", text(&provenance.meta.sha256)),
+            ("入力ファイル", source_name(&provenance.input)),
+            ("入力ファイル SHA-256", text(&provenance.input.sha256)),
+            ("言語", text(self.config.lng.as_str(self.config.lng))),
+            ("作成年", CellValue::Integer(self.config.created_year as i64)),
+        ];
+        if self.config.includes.is_empty() {
+            rows.push(("抽出条件", text("なし")));
+        }
+        for criteria in self.config.includes.iter() {
+            let (category, value) = criteria.display_names(self.config.lng);
+            rows.push(("抽出条件", CellValue::Text(format!("{}: {}", category, value))));
+        }
+        rows.extend(vec![
+            ("入力件数", CellValue::Integer(provenance.raw_records as i64)),
+            ("集計件数", CellValue::Integer(self.data.len() as i64)),
+            ("ツールバージョン",
+             CellValue::Text(format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")))),
+            ("作成日時", CellValue::Date(generated_at)),
+        ]);
+
+        let mut keys = Column::new(Header::new("項目".to_string(), false), None, rows.len());
+        let mut values = Column::new(Header::new("値".to_string(), false), None, rows.len());
+        for (key, value) in rows.into_iter() {
+            keys.contents.push(text(key));
+            values.contents.push(value);
+        }
+        Table::new(vec![keys, values])
+    }
+}
@@ -1,9 +1,13 @@
-#[derive(Debug)]
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Table {
     pub cols: Vec<Column>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TableWithMeta {
     pub meta: Vec<String>,
     pub special_case: SpecialCase,
@@ -26,38 +30,53 @@ impl TableWithMeta {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SpecialCase {
     None,
     SpaceAndHighlightOn4
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Column {
     pub header: Header,
-    pub contents: Vec<String>,
-    pub footer: Option<String>,
+    pub contents: Vec<CellValue>,
+    pub footer: Option<CellValue>,
+    /// How content cells derive from other cells, by row. Rows past the end are plain values.
+    #[serde(skip)]
+    pub formulas: Vec<Option<Formula>>,
+    #[serde(skip)]
+    pub footer_formula: Option<Formula>,
 }
 
 impl Column {
-    pub fn new(header: Header, footer: Option<String>, capacity: usize) -> Self {
+    pub fn new(header: Header, footer: Option<CellValue>, capacity: usize) -> Self {
         Column {
             header,
-            contents: Vec::<String>::with_capacity(capacity),
+            contents: Vec::<CellValue>::with_capacity(capacity),
             footer,
+            formulas: Vec::<Option<Formula>>::new(),
+            footer_formula: None,
         }
     }
 
-    pub fn from_contents(header: Header, contents: Vec<String>, footer: Option<String>) -> Self {
+    pub fn from_contents(header: Header, contents: Vec<CellValue>, footer: Option<CellValue>)
+        -> Self {
         Column {
             header,
             contents,
-            footer
+            footer,
+            formulas: Vec::<Option<Formula>>::new(),
+            footer_formula: None,
         }
     }
+
+    pub fn formula(&self, row: usize) -> Option<&Formula> {
+        self.formulas.get(row).and_then(|f| f.as_ref())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Header {
     pub text: String,
     pub highlight: bool,
@@ -68,3 +87,87 @@ impl Header {
         Header { text, highlight }
     }
 }
+
+/// Typed value of a table cell. Writers pick the cell format from the variant.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CellValue {
+    Text(String),
+    Integer(i64),
+    Float(f64),
+    /// Percentage in points, `12.5` for 12.5%
+    Percentage(f64),
+    Date(NaiveDateTime),
+    Empty,
+}
+
+impl CellValue {
+    /// Numeric value of integers, floats and percentages (in points).
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            CellValue::Integer(n) => Some(*n as f64),
+            CellValue::Float(n) | CellValue::Percentage(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CellValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CellValue::Text(text) => f.write_str(text),
+            CellValue::Integer(n) => write!(f, "{}", n),
+            CellValue::Float(n) => write!(f, "{}", n),
+            CellValue::Percentage(n) => write!(f, "{:.2}%", n),
+            CellValue::Date(date) => write!(f, "{}", date.format("%Y-%m-%d %H:%M")),
+            CellValue::Empty => Ok(()),
+        }
+    }
+}
+
+impl From<String> for CellValue {
+    fn from(text: String) -> Self {
+        CellValue::Text(text)
+    }
+}
+
+impl From<&str> for CellValue {
+    fn from(text: &str) -> Self {
+        CellValue::Text(text.to_string())
+    }
+}
+
+impl From<usize> for CellValue {
+    fn from(n: usize) -> Self {
+        CellValue::Integer(n as i64)
+    }
+}
+
+/// Derivation of a cell from other cells, for writers producing live spreadsheets.
+///
+/// The cell value stays the computed result, so writers can ignore formulas.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Formula {
+    /// Sum of the contents of the cell's column
+    ColumnSum,
+    /// Share of `count` in `total`, in points
+    Share { count: CellRef, total: Total },
+}
+
+/// Content cell of a table, by column and row index.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CellRef {
+    /// Cell of the same table
+    Table { col: usize, row: usize },
+    /// Cell of the `CrosstabType::N` version of a crosstab(%) table
+    Counts { col: usize, row: usize },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Total {
+    Constant(f64),
+    /// Footer of a column of the same table
+    Footer(usize),
+    /// Cells of the row of `first`, from its column to `last_col`
+    RowSum { first: CellRef, last_col: usize },
+}
@@ -9,6 +9,18 @@ pub enum RustlyzerError {
     MetadataWrongFormat(String),
     #[error("Wrong format for csv input data: {0}")]
     CsvInputWrongFormat(String),
+    #[error("Wrong format for json input data: {0}")]
+    JsonInputWrongFormat(String),
+    #[error("Wrong format for xlsx input data: {0}")]
+    XlsxInputWrongFormat(String),
+    #[error("Wrong format for job file: {0}")]
+    JobWrongFormat(String),
+    #[error("Wrong format for request: {0}")]
+    RequestWrongFormat(String),
+    #[error("Request body larger than {0} bytes")]
+    RequestTooLarge(u64),
+    #[error("Wrong format for theme: {0}")]
+    ThemeWrongFormat(String),
     #[error("Index out of range")]
     IndexOutOfRange,
     #[error("Header not defined")]
@@ -51,15 +63,38 @@ pub enum RustlyzerError {
 
 impl From<csv::Error> for RustlyzerError {
     fn from(err: csv::Error) -> Self {
-        RustlyzerError::CsvInputWrongFormat(format!("{:?}", err))
+        match err.kind() {
+            // Input not valid in its encoding, the message names the line
+            csv::ErrorKind::Io(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+                RustlyzerError::CsvInputWrongFormat(e.to_string())
+            },
+            _ => RustlyzerError::CsvInputWrongFormat(format!("{:?}", err)),
+        }
     }
 }
 
-// impl std::convert::From<RustlyzerError> for PyErr {
-//     fn from(err: RustlyzerError) -> PyErr {
-//         PyOSError::new_err(err.to_string())
-//     }
-// }
+#[cfg(feature = "python")]
+impl From<RustlyzerError> for pyo3::PyErr {
+    fn from(err: RustlyzerError) -> pyo3::PyErr {
+        use crate::python as py;
+        let msg = err.to_string();
+        match err {
+            RustlyzerError::MetadataWrongFormat(_) => py::MetadataWrongFormatError::new_err(msg),
+            RustlyzerError::CsvInputWrongFormat(_) => py::CsvInputWrongFormatError::new_err(msg),
+            RustlyzerError::JsonInputWrongFormat(_) => py::JsonInputWrongFormatError::new_err(msg),
+            RustlyzerError::XlsxInputWrongFormat(_) => py::XlsxInputWrongFormatError::new_err(msg),
+            RustlyzerError::JobWrongFormat(_) => py::JobWrongFormatError::new_err(msg),
+            RustlyzerError::InvalidDataError { .. } => py::InvalidDataError::new_err(msg),
+            RustlyzerError::InvalidConfigValError { .. }
+            | RustlyzerError::InvalidConfigItemError(_)
+            | RustlyzerError::ThemeWrongFormat(_) => py::InvalidConfigError::new_err(msg),
+            RustlyzerError::IoError(_) => py::ReportIoError::new_err(msg),
+            RustlyzerError::XlsxError(_) => py::XlsxError::new_err(msg),
+            RustlyzerError::InternalError(_) => py::InternalError::new_err(msg),
+            _ => py::RustlyzerException::new_err(msg),
+        }
+    }
+}
 
 impl From<std::io::Error> for RustlyzerError {
     fn from(err: std::io::Error) -> Self {
@@ -0,0 +1,403 @@
+//! C-compatible API, exported from the `cdylib` build.
+//!
+//! Every function returns a [`ReportToolStatus`]. On failure a UTF-8, NUL-terminated
+//! description is stored in `*err` (when `err` is not null) and has to be released with
+//! [`report_tool_string_free`]. The header is generated with `cbindgen` into
+//! `include/report_tool.h`.
+//!
+//! Inputs are read and reports written with the default options: the C API has no way to pick
+//! the csv dialect, a column mapping, lenient reading, the sheets, the xlsx options or a theme.
+//! Use a job file with the command line for those.
+use crate::ds::config::provide_include_criteria_constants;
+use crate::ds::{CsvOptions, DataSet, DataSetConfig};
+use crate::errors::RustlyzerError;
+use crate::writer::{self, Sheet, Theme, XlsxOptions};
+use libc::{c_char, size_t};
+use std::ffi::{CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+
+/// Outcome of a call into the library.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReportToolStatus {
+    Ok = 0,
+    /// A required pointer argument was null
+    NullArgument = 1,
+    /// A string argument was not valid UTF-8
+    InvalidUtf8 = 2,
+    MetadataWrongFormat = 3,
+    /// The csv, json or xlsx input could not be read
+    CsvInputWrongFormat = 4,
+    /// Invalid language or include criteria
+    InvalidConfig = 5,
+    /// A record failed validation
+    InvalidData = 6,
+    Io = 7,
+    Xlsx = 8,
+    Internal = 9,
+}
+
+impl From<&RustlyzerError> for ReportToolStatus {
+    fn from(err: &RustlyzerError) -> Self {
+        match err {
+            RustlyzerError::MetadataWrongFormat(_) => ReportToolStatus::MetadataWrongFormat,
+            RustlyzerError::CsvInputWrongFormat(_)
+            | RustlyzerError::JsonInputWrongFormat(_)
+            | RustlyzerError::XlsxInputWrongFormat(_) => ReportToolStatus::CsvInputWrongFormat,
+            RustlyzerError::InvalidConfigValError { .. }
+            | RustlyzerError::InvalidConfigItemError(_)
+            | RustlyzerError::JobWrongFormat(_)
+            | RustlyzerError::ThemeWrongFormat(_) => ReportToolStatus::InvalidConfig,
+            RustlyzerError::InvalidDataError { .. } => ReportToolStatus::InvalidData,
+            RustlyzerError::IoError(_) => ReportToolStatus::Io,
+            RustlyzerError::XlsxError(_) => ReportToolStatus::Xlsx,
+            _ => ReportToolStatus::Internal,
+        }
+    }
+}
+
+/// Opaque handle to a loaded `DataSet`.
+pub struct ReportToolDataSet {
+    dataset: DataSet,
+}
+
+/// Error raised while converting the C arguments, before reaching the library.
+enum FfiError {
+    Null(&'static str),
+    Utf8(&'static str),
+    Lib(RustlyzerError),
+}
+
+impl From<RustlyzerError> for FfiError {
+    fn from(err: RustlyzerError) -> Self {
+        FfiError::Lib(err)
+    }
+}
+
+impl From<std::io::Error> for FfiError {
+    fn from(err: std::io::Error) -> Self {
+        FfiError::Lib(err.into())
+    }
+}
+
+impl FfiError {
+    fn status(&self) -> ReportToolStatus {
+        match self {
+            FfiError::Null(_) => ReportToolStatus::NullArgument,
+            FfiError::Utf8(_) => ReportToolStatus::InvalidUtf8,
+            FfiError::Lib(e) => e.into(),
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            FfiError::Null(arg) => format!("argument `{}` is null", arg),
+            FfiError::Utf8(arg) => format!("argument `{}` is not valid UTF-8", arg),
+            FfiError::Lib(e) => e.to_string(),
+        }
+    }
+}
+
+/// Run `f`, turning errors and panics into a status code and an error string.
+fn guard<F>(err: *mut *mut c_char, f: F) -> ReportToolStatus
+where
+    F: FnOnce() -> Result<(), FfiError>,
+{
+    if !err.is_null() {
+        unsafe { *err = ptr::null_mut() };
+    }
+    let res = panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|_| {
+        Err(FfiError::Lib(RustlyzerError::InternalError("panic in report-tool".to_string())))
+    });
+    match res {
+        Ok(()) => ReportToolStatus::Ok,
+        Err(e) => {
+            if !err.is_null() {
+                unsafe { *err = into_c_string(e.message()) };
+            }
+            e.status()
+        }
+    }
+}
+
+fn into_c_string(s: String) -> *mut c_char {
+    // Interior NULs cannot be represented, drop them rather than losing the whole message.
+    let s = s.replace('\0', "");
+    CString::new(s).map(CString::into_raw).unwrap_or(ptr::null_mut())
+}
+
+unsafe fn str_arg<'a>(p: *const c_char, name: &'static str) -> Result<&'a str, FfiError> {
+    if p.is_null() {
+        return Err(FfiError::Null(name));
+    }
+    CStr::from_ptr(p).to_str().map_err(|_| FfiError::Utf8(name))
+}
+
+unsafe fn buffer_arg<'a>(p: *const u8, len: size_t, name: &'static str) -> Result<&'a str,
+    FfiError> {
+    if len == 0 {
+        return Ok("");
+    }
+    if p.is_null() {
+        return Err(FfiError::Null(name));
+    }
+    std::str::from_utf8(std::slice::from_raw_parts(p, len)).map_err(|_| FfiError::Utf8(name))
+}
+
+unsafe fn config_arg(
+    lang: *const c_char,
+    created_year: u16,
+    categories: *const *const c_char,
+    values: *const *const c_char,
+    includes_len: size_t,
+) -> Result<DataSetConfig, FfiError> {
+    let lang = str_arg(lang, "lang")?;
+    let mut includes = Vec::<(String, String)>::new();
+    if includes_len > 0 {
+        if categories.is_null() {
+            return Err(FfiError::Null("categories"));
+        }
+        if values.is_null() {
+            return Err(FfiError::Null("values"));
+        }
+        for i in 0..includes_len {
+            let cat = str_arg(*categories.add(i), "categories")?;
+            let val = str_arg(*values.add(i), "values")?;
+            includes.push((cat.to_string(), val.to_string()));
+        }
+    }
+    Ok(DataSetConfig::new_with_includes(lang.to_string(), created_year, includes)?)
+}
+
+unsafe fn store_dataset(dataset: DataSet, out: *mut *mut ReportToolDataSet) {
+    *out = Box::into_raw(Box::new(ReportToolDataSet { dataset }));
+}
+
+/// Load a `DataSet` from a metadata json file and a csv input file.
+///
+/// `categories` and `values` are parallel arrays of `includes_len` include criteria,
+/// e.g. `"gender"` / `"男性"`. On success `*out` receives a handle to be released with
+/// `report_tool_dataset_free`.
+///
+/// The csv input is read with the default options: UTF-8 or Shift_JIS as detected, comma
+/// separated, the header on the first line and failing on the first invalid row. An input
+/// ending in `.json`, `.ndjson`, `.jsonl` or `.xlsx` is read in that format, from the first
+/// worksheet of a workbook. Other input options can not be given through the C API.
+///
+/// # Safety
+///
+/// Pointers must be null or valid for the documented length; strings must be NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn report_tool_dataset_from_files(
+    meta_path: *const c_char,
+    input_path: *const c_char,
+    lang: *const c_char,
+    created_year: u16,
+    categories: *const *const c_char,
+    values: *const *const c_char,
+    includes_len: size_t,
+    out: *mut *mut ReportToolDataSet,
+    err: *mut *mut c_char,
+) -> ReportToolStatus {
+    guard(err, || {
+        if out.is_null() {
+            return Err(FfiError::Null("out"));
+        }
+        let meta_path = str_arg(meta_path, "meta_path")?;
+        let input_path = str_arg(input_path, "input_path")?;
+        let config = config_arg(lang, created_year, categories, values, includes_len)?;
+        let dataset = DataSet::from_paths(Path::new(meta_path), config, Path::new(input_path),
+                                          &CsvOptions::default())?;
+        store_dataset(dataset, out);
+        Ok(())
+    })
+}
+
+/// Load a `DataSet` from in-memory metadata json and csv input, both UTF-8 and not
+/// NUL-terminated. Otherwise identical to `report_tool_dataset_from_files`, the input being
+/// csv only.
+///
+/// # Safety
+///
+/// `meta` and `input` must be null or valid for reads of `meta_len` and `input_len` bytes;
+/// `lang` and the include criteria must be NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn report_tool_dataset_from_buffers(
+    meta: *const u8,
+    meta_len: size_t,
+    input: *const u8,
+    input_len: size_t,
+    lang: *const c_char,
+    created_year: u16,
+    categories: *const *const c_char,
+    values: *const *const c_char,
+    includes_len: size_t,
+    out: *mut *mut ReportToolDataSet,
+    err: *mut *mut c_char,
+) -> ReportToolStatus {
+    guard(err, || {
+        if out.is_null() {
+            return Err(FfiError::Null("out"));
+        }
+        let meta = buffer_arg(meta, meta_len, "meta")?;
+        let input = buffer_arg(input, input_len, "input")?;
+        let config = config_arg(lang, created_year, categories, values, includes_len)?;
+        store_dataset(DataSet::from_data(meta, config, input)?, out);
+        Ok(())
+    })
+}
+
+/// Number of records left in the dataset after validation and filtering.
+///
+/// # Safety
+///
+/// `dataset` must be null or a handle returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn report_tool_dataset_len(dataset: *const ReportToolDataSet) -> size_t {
+    dataset.as_ref().map(|ds| ds.dataset.data.len()).unwrap_or(0)
+}
+
+/// Release a dataset handle. Null is ignored.
+///
+/// # Safety
+///
+/// `dataset` must be null or a handle returned by this library, released only once.
+#[no_mangle]
+pub unsafe extern "C" fn report_tool_dataset_free(dataset: *mut ReportToolDataSet) {
+    if !dataset.is_null() {
+        drop(Box::from_raw(dataset));
+    }
+}
+
+/// Write the full xlsx report for `dataset` to `output_path`: every sheet but the index and
+/// data-quality sheets, in the default order, with the default xlsx options and theme. Sheets
+/// and options can not be chosen through the C API.
+///
+/// # Safety
+///
+/// `dataset` must be a handle returned by this library; strings must be NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn report_tool_write_workbook(
+    dataset: *const ReportToolDataSet,
+    output_path: *const c_char,
+    err: *mut *mut c_char,
+) -> ReportToolStatus {
+    guard(err, || {
+        let dataset = dataset.as_ref().ok_or(FfiError::Null("dataset"))?;
+        let output_path = str_arg(output_path, "output_path")?;
+        writer::write_output_file(&dataset.dataset, &Sheet::get_all(), &XlsxOptions::default(),
+                                  &Theme::default(), Path::new(output_path))?;
+        Ok(())
+    })
+}
+
+/// Write the full xlsx report for `dataset` into a buffer, as `report_tool_write_workbook`
+/// does. `*out` and `*out_len` receive the workbook bytes, which must be released with
+/// `report_tool_bytes_free`.
+///
+/// # Safety
+///
+/// `dataset` must be a handle returned by this library; `out` and `out_len` must be valid for
+/// writes.
+#[no_mangle]
+pub unsafe extern "C" fn report_tool_write_workbook_bytes(
+    dataset: *const ReportToolDataSet,
+    out: *mut *mut u8,
+    out_len: *mut size_t,
+    err: *mut *mut c_char,
+) -> ReportToolStatus {
+    guard(err, || {
+        let dataset = dataset.as_ref().ok_or(FfiError::Null("dataset"))?;
+        if out.is_null() {
+            return Err(FfiError::Null("out"));
+        }
+        if out_len.is_null() {
+            return Err(FfiError::Null("out_len"));
+        }
+        let bytes = writer::write_output_bytes(&dataset.dataset, &Sheet::get_all(),
+                                               &XlsxOptions::default(), &Theme::default())?
+            .into_boxed_slice();
+        *out_len = bytes.len();
+        *out = Box::into_raw(bytes) as *mut u8;
+        Ok(())
+    })
+}
+
+/// Load the inputs and write the full xlsx report in one call, like `create_output_file`.
+///
+/// # Safety
+///
+/// Pointers must be null or valid for the documented length; strings must be NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn report_tool_create_output_file(
+    meta_path: *const c_char,
+    input_path: *const c_char,
+    output_path: *const c_char,
+    lang: *const c_char,
+    created_year: u16,
+    categories: *const *const c_char,
+    values: *const *const c_char,
+    includes_len: size_t,
+    err: *mut *mut c_char,
+) -> ReportToolStatus {
+    let mut dataset = ptr::null_mut();
+    let status = report_tool_dataset_from_files(meta_path, input_path, lang, created_year,
+                                                categories, values, includes_len, &mut dataset,
+                                                err);
+    if status != ReportToolStatus::Ok {
+        return status;
+    }
+    let status = report_tool_write_workbook(dataset, output_path, err);
+    report_tool_dataset_free(dataset);
+    status
+}
+
+/// Available include criteria, as the json serialization of
+/// `provide_include_criteria_constants`. `*out` must be released with
+/// `report_tool_string_free`.
+///
+/// # Safety
+///
+/// `out` must be valid for writes; `err` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn report_tool_include_criteria_constants(
+    out: *mut *mut c_char,
+    err: *mut *mut c_char,
+) -> ReportToolStatus {
+    guard(err, || {
+        if out.is_null() {
+            return Err(FfiError::Null("out"));
+        }
+        let json = serde_json::to_string(&provide_include_criteria_constants())
+            .map_err(|e| RustlyzerError::InternalError(e.to_string()))?;
+        *out = into_c_string(json);
+        Ok(())
+    })
+}
+
+/// Release a string returned by this library. Null is ignored.
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by this library, released only once.
+#[no_mangle]
+pub unsafe extern "C" fn report_tool_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Release a buffer returned by this library. Null is ignored.
+///
+/// # Safety
+///
+/// `bytes` must be null or a buffer returned by this library with its length `len`, released
+/// only once.
+#[no_mangle]
+pub unsafe extern "C" fn report_tool_bytes_free(bytes: *mut u8, len: size_t) {
+    if !bytes.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(bytes, len)));
+    }
+}
@@ -3,6 +3,7 @@ use std::fmt::Debug;
 use crate::errors::RustlyzerError;
 use convert_case::{Casing, Case};
 
+pub mod sha256;
 pub mod strings;
 
 pub trait CustomHelpers<T> {
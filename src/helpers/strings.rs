@@ -2,4 +2,26 @@ use std::iter::FromIterator;
 
 pub fn into_clean_string(s: &String) -> String {
     String::from_iter(s.chars().filter(|&c| c != '\u{0}').collect::<Vec<char>>())
-}
\ No newline at end of file
+}
+
+/// Width of `s` in a monospace grid, with East Asian wide and fullwidth characters (kanji, kana,
+/// hangul, fullwidth forms) taking two columns.
+pub fn display_width(s: &str) -> usize {
+    s.chars().map(|c| if is_wide(c) { 2 } else { 1 }).sum()
+}
+
+fn is_wide(c: char) -> bool {
+    matches!(c as u32,
+        0x1100..=0x115f
+        | 0x2e80..=0x303e
+        | 0x3041..=0x33ff
+        | 0x3400..=0x4dbf
+        | 0x4e00..=0x9fff
+        | 0xa000..=0xa4cf
+        | 0xac00..=0xd7a3
+        | 0xf900..=0xfaff
+        | 0xfe30..=0xfe4f
+        | 0xff00..=0xff60
+        | 0xffe0..=0xffe6
+        | 0x20000..=0x3fffd)
+}
@@ -0,0 +1,185 @@
+use super::ReportJob;
+use crate::errors::RustlyzerError;
+use crate::writer::{self, JobRun};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+/// List of jobs to run as one batch, loaded from a json or toml manifest.
+///
+/// ```toml
+/// [[jobs]]
+/// meta = "survey_a.json"
+/// input = "survey_a.csv"
+/// output = "out/survey_a.xlsx"
+/// created_year = 2021
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BatchManifest {
+    pub jobs: Vec<ReportJob>,
+}
+
+impl BatchManifest {
+    /// Load a manifest. The format is chosen by extension (`.json` or `.toml`).
+    /// Relative paths are resolved against the directory containing the manifest.
+    pub fn from_file(path: &Path) -> Result<Self, RustlyzerError> {
+        let mut content = String::new();
+        File::open(path)?.read_to_string(&mut content)?;
+        let mut manifest = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str::<BatchManifest>(&content)
+                .map_err(|e| RustlyzerError::JobWrongFormat(e.to_string()))?,
+            Some("toml") => toml::from_str::<BatchManifest>(&content)
+                .map_err(|e| RustlyzerError::JobWrongFormat(e.to_string()))?,
+            ext => return Err(RustlyzerError::JobWrongFormat(format!(
+                "unsupported manifest extension {:?}, expected json or toml", ext)))
+        };
+        if let Some(base_dir) = path.parent() {
+            manifest.jobs.iter_mut().for_each(|job| job.resolve_paths(base_dir));
+        }
+        Ok(manifest)
+    }
+}
+
+/// Jobs for every `<name>.json`/`<name>.csv` pair in `dir`, each writing
+/// `<output_dir>/<name>.xlsx`.
+///
+/// Returns the jobs and the json or csv files that have no counterpart.
+pub fn jobs_from_dir(
+    dir: &Path,
+    output_dir: &Path,
+    lng: &str,
+    created_year: u16,
+    includes: Vec<(String, String)>,
+) -> Result<(Vec<ReportJob>, Vec<PathBuf>), RustlyzerError> {
+    let mut files = Vec::<PathBuf>::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+
+    let mut jobs = Vec::<ReportJob>::new();
+    let mut unmatched = Vec::<PathBuf>::new();
+    for path in files.iter() {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => {
+                let input = path.with_extension("csv");
+                if !files.contains(&input) {
+                    unmatched.push(path.to_owned());
+                    continue;
+                }
+                let stem = path.file_stem().unwrap_or_default();
+                let output = output_dir.join(stem).with_extension("xlsx");
+                jobs.push(ReportJob::new(
+                    &path.to_string_lossy(),
+                    &input.to_string_lossy(),
+                    &output.to_string_lossy(),
+                    lng,
+                    created_year,
+                    includes.clone(),
+                ));
+            },
+            Some("csv") => {
+                if !files.contains(&path.with_extension("json")) {
+                    unmatched.push(path.to_owned());
+                }
+            },
+            _ => (),
+        }
+    }
+    Ok((jobs, unmatched))
+}
+
+/// Result of one job in a batch.
+#[derive(Debug)]
+pub struct BatchOutcome {
+    pub job: ReportJob,
+    /// What the job did, or the error that stopped it
+    pub result: Result<JobRun, RustlyzerError>,
+}
+
+/// Results of every job in a batch, in the order the jobs were given.
+#[derive(Debug)]
+pub struct BatchSummary {
+    pub outcomes: Vec<BatchOutcome>,
+}
+
+impl BatchSummary {
+    pub fn succeeded(&self) -> Vec<&BatchOutcome> {
+        self.outcomes.iter().filter(|o| o.result.is_ok()).collect()
+    }
+
+    pub fn failed(&self) -> Vec<&BatchOutcome> {
+        self.outcomes.iter().filter(|o| o.result.is_err()).collect()
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.outcomes.iter().all(|o| o.result.is_ok())
+    }
+}
+
+impl fmt::Display for BatchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let succeeded = self.succeeded();
+        let failed = self.failed();
+        writeln!(f, "{} succeeded, {} failed", succeeded.len(), failed.len())?;
+        for outcome in succeeded.iter() {
+            if let Ok(run) = &outcome.result {
+                write!(f, "  ok     {:?} in {} ms", outcome.job.output, run.total_time)?;
+                if let Some((rows, rejected_path)) = &run.rejected {
+                    write!(f, ", {} rows rejected, listed in {:?}", rows, rejected_path)?;
+                }
+                writeln!(f)?;
+            }
+        }
+        for outcome in failed.iter() {
+            if let Err(e) = &outcome.result {
+                writeln!(f, "  failed {:?}: {}", outcome.job.input, e)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Validate and run every job in parallel.
+///
+/// Jobs print nothing, what each did is in the summary. A failing (or panicking)
+/// job is recorded in the summary and does not stop the remaining jobs. `threads`
+/// limits the number of jobs run at once, defaulting to the number of cpus.
+pub fn run_batch(jobs: Vec<ReportJob>, threads: Option<usize>) -> Result<BatchSummary,
+    RustlyzerError> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads.unwrap_or(0))
+        .build()
+        .map_err(|e| RustlyzerError::InternalError(e.to_string()))?;
+    let outcomes = pool.install(|| {
+        jobs.into_par_iter()
+            .map(|job| {
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    job.validate()?;
+                    writer::run_job(&job)
+                }))
+                .unwrap_or_else(|cause| Err(RustlyzerError::InternalError(panic_message(cause))));
+                BatchOutcome { job, result }
+            })
+            .collect::<Vec<BatchOutcome>>()
+    });
+    Ok(BatchSummary { outcomes })
+}
+
+fn panic_message(cause: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(s) = cause.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = cause.downcast_ref::<String>() {
+        s.to_owned()
+    } else {
+        "job panicked".to_string()
+    }
+}
@@ -0,0 +1,235 @@
+use crate::ds::{CsvOptions, DataSetConfig, Language};
+use crate::errors::RustlyzerError;
+use crate::helpers::EnumAttrs;
+use crate::writer::{Sheet, Theme, XlsxOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+pub mod batch;
+
+/// Declarative description of a full report run.
+///
+/// Can be loaded from a json or toml job file, so the exact run can be
+/// version-controlled and repeated. Relative paths in a job file are resolved
+/// against the directory containing the job file.
+///
+/// ```toml
+/// meta = "survey.json"
+/// input = "survey.csv"
+/// output = "survey.xlsx"
+/// lang = "ja"
+/// created_year = 2021
+/// sheets = ["user_graph", "aggregate", "crosstab_n", "crosstab_perc"]
+///
+/// theme = "brand.json"
+///
+/// [csv]
+/// encoding = "shift_jis"
+/// delimiter = "\t"
+/// header_offset = 1
+/// lenient = true
+///
+/// [csv.columns]
+/// gender = "性別"
+/// birth_year = "生年"
+///
+/// [xlsx]
+/// formulas = true
+///
+/// [includes]
+/// gender = ["男性"]
+/// marital-status = ["既婚", "未婚"]
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ReportJob {
+    /// Metadata json describing the custom fields
+    pub meta: PathBuf,
+    /// Survey responses, as csv or, by extension, json (`.json`), json lines (`.ndjson`,
+    /// `.jsonl`) or a workbook (`.xlsx`)
+    pub input: PathBuf,
+    /// Dialect of the csv input, and the sheet of a workbook input
+    #[serde(default)]
+    pub csv: CsvOptions,
+    /// Path the report is written to (`.json` for a json report, `.html` for an html page,
+    /// otherwise xlsx)
+    pub output: PathBuf,
+    /// Language for data representation ("en" or "ja")
+    #[serde(default = "default_lang")]
+    pub lang: String,
+    /// Year the data was collected
+    pub created_year: u16,
+    /// Include criteria, as category -> accepted values
+    #[serde(default)]
+    pub includes: BTreeMap<String, Vec<String>>,
+    /// Sheets to produce
+    #[serde(default = "Sheet::get_all")]
+    pub sheets: Vec<Sheet>,
+    /// Options of the xlsx output
+    #[serde(default)]
+    pub xlsx: XlsxOptions,
+    /// Theme json file of the report (the built-in theme by default)
+    #[serde(default)]
+    pub theme: Option<PathBuf>,
+}
+
+pub(crate) fn default_lang() -> String {
+    "ja".to_string()
+}
+
+/// Check `lang` is a known language and `sheets` is a non-empty list without duplicates, with
+/// the index first if selected. Problems with `sheets` are reported through `wrong_format`.
+pub(crate) fn validate_lang_and_sheets(
+    lang: &str,
+    sheets: &[Sheet],
+    wrong_format: fn(String) -> RustlyzerError,
+) -> Result<(), RustlyzerError> {
+    let languages = Language::get_all_string(Language::En);
+    if !languages.iter().any(|l| l == lang) {
+        return Err(RustlyzerError::InvalidConfigValError {
+            config_item: "lang".to_string(),
+            val: lang.to_owned(),
+            expected_values: languages,
+        });
+    }
+    if sheets.is_empty() {
+        return Err(wrong_format("no sheets selected".to_string()));
+    }
+    for (i, sheet) in sheets.iter().enumerate() {
+        if sheets[..i].contains(sheet) {
+            return Err(wrong_format(format!("sheet {} listed more than once", sheet.id())));
+        }
+        if i > 0 && *sheet == Sheet::Index {
+            return Err(wrong_format("sheet index has to come first".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Include criteria map flattened to (category, value) pairs.
+pub(crate) fn include_tuples(includes: &BTreeMap<String, Vec<String>>) -> Vec<(String, String)> {
+    includes
+        .iter()
+        .flat_map(|(cat, vals)| vals.iter().map(move |val| (cat.to_owned(), val.to_owned())))
+        .collect::<Vec<(String, String)>>()
+}
+
+impl ReportJob {
+    /// Create a job producing every sheet, equivalent to a `create_output_file` call.
+    pub fn new(
+        meta: &str,
+        input: &str,
+        output: &str,
+        lng: &str,
+        created_year: u16,
+        includes: Vec<(String, String)>,
+    ) -> Self {
+        let mut include_map = BTreeMap::<String, Vec<String>>::new();
+        for (cat, val) in includes.into_iter() {
+            include_map.entry(cat).or_insert_with(Vec::new).push(val);
+        }
+        ReportJob {
+            meta: PathBuf::from(meta),
+            input: PathBuf::from(input),
+            csv: CsvOptions::default(),
+            output: PathBuf::from(output),
+            lang: lng.to_string(),
+            created_year,
+            includes: include_map,
+            sheets: Sheet::get_all(),
+            xlsx: XlsxOptions::default(),
+            theme: None,
+        }
+    }
+
+    /// Load a job file. The format is chosen by extension (`.json` or `.toml`).
+    /// The loaded job is validated before being returned.
+    pub fn from_file(path: &Path) -> Result<Self, RustlyzerError> {
+        let mut content = String::new();
+        File::open(path)?.read_to_string(&mut content)?;
+        let mut job = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json(&content)?,
+            Some("toml") => Self::from_toml(&content)?,
+            ext => return Err(RustlyzerError::JobWrongFormat(format!(
+                "unsupported job file extension {:?}, expected json or toml", ext)))
+        };
+        if let Some(base_dir) = path.parent() {
+            job.resolve_paths(base_dir);
+        }
+        job.validate()?;
+        Ok(job)
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, RustlyzerError> {
+        serde_json::from_str::<ReportJob>(content)
+            .map_err(|e| RustlyzerError::JobWrongFormat(e.to_string()))
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, RustlyzerError> {
+        toml::from_str::<ReportJob>(content)
+            .map_err(|e| RustlyzerError::JobWrongFormat(e.to_string()))
+    }
+
+    pub fn to_json(&self) -> Result<String, RustlyzerError> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| RustlyzerError::InternalError(e.to_string()))
+    }
+
+    /// Make relative `meta`, `input`, `output` and `theme` paths relative to `base_dir`.
+    pub fn resolve_paths(&mut self, base_dir: &Path) {
+        let paths = vec![&mut self.meta, &mut self.input, &mut self.output];
+        for path in paths.into_iter().chain(self.theme.as_mut()) {
+            if path.is_relative() {
+                *path = base_dir.join(&*path);
+            }
+        }
+    }
+
+    /// Check the job can be run: known language and sheets, valid include
+    /// criteria, existing input files and a valid theme.
+    pub fn validate(&self) -> Result<(), RustlyzerError> {
+        validate_lang_and_sheets(&self.lang, &self.sheets, RustlyzerError::JobWrongFormat)?;
+        self.config()?;
+        self.csv.delimiter_and_quote()?;
+        for path in [&self.meta, &self.input].iter() {
+            if !path.is_file() {
+                return Err(RustlyzerError::IoError(format!("{:?} does not exist", path)));
+            }
+        }
+        self.theme()?;
+        Ok(())
+    }
+
+    /// `DataSetConfig` described by the job.
+    pub fn config(&self) -> Result<DataSetConfig, RustlyzerError> {
+        DataSetConfig::new_with_includes(self.lang.to_owned(), self.created_year,
+                                         self.include_tuples())
+    }
+
+    /// Theme of the report, loaded from `theme` if set.
+    pub fn theme(&self) -> Result<Theme, RustlyzerError> {
+        match &self.theme {
+            Some(path) => Theme::from_file(path),
+            None => Ok(Theme::default()),
+        }
+    }
+
+    /// Include criteria as (category, value) pairs.
+    pub fn include_tuples(&self) -> Vec<(String, String)> {
+        include_tuples(&self.includes)
+    }
+
+    /// Csv the rows rejected by a lenient run are written to, `<output>.rejected.csv` next to
+    /// the report.
+    pub fn rejected_path(&self) -> PathBuf {
+        self.output.with_extension("rejected.csv")
+    }
+
+    /// Run the job, writing the report to `output`.
+    pub fn run(&self) -> Result<u128, RustlyzerError> {
+        crate::writer::create_output_file_from_job(self)
+    }
+}
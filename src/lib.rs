@@ -4,6 +4,11 @@
 
 pub mod ds;
 pub mod errors;
+pub mod ffi;
 mod helpers;
+pub mod job;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod server;
 mod tests;
 pub mod writer;
\ No newline at end of file
@@ -1,38 +1,308 @@
 #![allow(dead_code)]
 #![allow(warnings)]
-use report_tool::ds::{DataSet, DataSetConfig};
-use report_tool::writer;
-use std::fs::File;
-use std::io::prelude::*;
-use std::path::Path;
-use xlsxwriter::Workbook;
-
-const DATA_PATH: &'static str = "./";
-fn main() {
+use chrono::prelude::*;
+use report_tool::ds::{ColumnMapping, CsvOptions, InputEncoding, InputSheet};
+use report_tool::errors::RustlyzerError;
+use report_tool::job::ReportJob;
+use report_tool::job::batch::{self, BatchManifest};
+use report_tool::server::ReportServer;
+use report_tool::writer::{self, Sheet, XlsxOptions};
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+/// Generates an xlsx report from survey metadata (json) and responses (csv).
+#[derive(Debug, StructOpt)]
+#[structopt(name = "report-tool")]
+enum Command {
+    /// Generate a single report workbook
+    Generate {
+        /// Path to the metadata json describing the custom fields
+        #[structopt(parse(from_os_str))]
+        meta: PathBuf,
+        /// Path to the survey responses: csv, a json array (`.json`) or json lines (`.ndjson`,
+        /// `.jsonl`) of records keyed by the csv headers, or a workbook (`.xlsx`)
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+        #[structopt(flatten)]
+        csv: CsvFlags,
+        /// Path the report is written to: an xlsx workbook, or a json report or html page if it
+        /// ends in `.json` or `.html`
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+        /// Language used for labels in the report
+        #[structopt(long, default_value = "ja", possible_values = &["en", "ja"])]
+        lang: String,
+        /// Year the data was collected (defaults to the current year)
+        #[structopt(long)]
+        created_year: Option<u16>,
+        /// Only include records matching `category=value`, e.g. `gender=男性`.
+        /// Can be repeated; values of the same category are OR-ed.
+        #[structopt(long = "include", number_of_values = 1, parse(try_from_str = parse_include))]
+        includes: Vec<(String, String)>,
+        /// Sheets to write, in order, as comma separated ids (defaults to every sheet but the
+        /// index): index, fkc_rawdata, it_rawdata, user_graph, aggregate, crosstab_n,
+        /// crosstab_perc, report_info, data_quality
+        #[structopt(long, use_delimiter = true, require_delimiter = true)]
+        sheets: Vec<Sheet>,
+        #[structopt(flatten)]
+        xlsx: XlsxFlags,
+        /// Theme json file with the colours, formats and font of the report
+        #[structopt(long, parse(from_os_str))]
+        theme: Option<PathBuf>,
+    },
+    /// Run a report described by a json or toml job file
+    Job {
+        /// Path to the job file
+        #[structopt(parse(from_os_str))]
+        job: PathBuf,
+        /// Only load and validate the job file, without generating the report
+        #[structopt(long)]
+        check: bool,
+    },
+    /// Generate one report per survey, in parallel
+    Batch {
+        /// Directory of `<name>.json`/`<name>.csv` pairs, or a json/toml manifest of jobs. The
+        /// options below apply to directory mode only and are refused with a manifest, whose jobs
+        /// carry their own
+        #[structopt(parse(from_os_str))]
+        source: PathBuf,
+        /// Directory reports are written to in directory mode (defaults to the source directory)
+        #[structopt(long, parse(from_os_str))]
+        output_dir: Option<PathBuf>,
+        /// Language used for labels in directory mode (defaults to ja)
+        #[structopt(long, possible_values = &["en", "ja"])]
+        lang: Option<String>,
+        /// Year the data was collected in directory mode (defaults to the current year)
+        #[structopt(long)]
+        created_year: Option<u16>,
+        /// Include criteria applied to every survey in directory mode, as `category=value`
+        #[structopt(long = "include", number_of_values = 1, parse(try_from_str = parse_include))]
+        includes: Vec<(String, String)>,
+        /// Dialect of the csv inputs in directory mode
+        #[structopt(flatten)]
+        csv: CsvFlags,
+        /// Sheets to write in directory mode, as comma separated ids (defaults to every sheet)
+        #[structopt(long, use_delimiter = true, require_delimiter = true)]
+        sheets: Vec<Sheet>,
+        /// Options of the workbooks in directory mode
+        #[structopt(flatten)]
+        xlsx: XlsxFlags,
+        /// Theme json file of the reports in directory mode
+        #[structopt(long, parse(from_os_str))]
+        theme: Option<PathBuf>,
+        /// Number of surveys processed at once (defaults to the number of cpus)
+        #[structopt(long)]
+        threads: Option<usize>,
+    },
+    /// Serve reports over http (`POST /report`, `GET /include-criteria`)
+    Serve {
+        /// Address to listen on
+        #[structopt(long, default_value = "127.0.0.1:8080")]
+        addr: String,
+        /// Largest request body accepted, in MiB
+        #[structopt(long, default_value = "64")]
+        max_body_mib: u64,
+        /// Number of requests handled at once
+        #[structopt(long, default_value = "4")]
+        workers: usize,
+    },
+}
+
+/// Dialect of the csv input.
+#[derive(StructOpt, Debug)]
+struct CsvFlags {
+    /// Encoding of the csv input: auto, utf8 or shift_jis (cp932)
+    #[structopt(long, default_value = "auto")]
+    encoding: InputEncoding,
+    /// Field separator of the csv input, a character or `tab`
+    #[structopt(long, default_value = ",", parse(try_from_str = parse_char))]
+    delimiter: char,
+    /// Quote character of the csv input
+    #[structopt(long, default_value = "\"", parse(try_from_str = parse_char))]
+    quote: char,
+    /// Lines before the header row of the csv input, skipped
+    #[structopt(long, default_value = "0")]
+    header_offset: usize,
+    /// Keep a byte order mark at the start of the csv input
+    #[structopt(long)]
+    keep_bom: bool,
+    /// Json file mapping the fields to the headers of the csv input
+    #[structopt(long, parse(try_from_str = parse_columns))]
+    columns: Option<ColumnMapping>,
+    /// Skip invalid rows of the csv input instead of failing, listing them in
+    /// `<output>.rejected.csv` and a data_quality sheet
+    #[structopt(long)]
+    lenient: bool,
+    /// Worksheet of an xlsx input, by name or by position from 0
+    #[structopt(long, default_value = "0")]
+    sheet: InputSheet,
+}
+
+impl From<CsvFlags> for CsvOptions {
+    fn from(flags: CsvFlags) -> Self {
+        CsvOptions {
+            encoding: flags.encoding,
+            strip_bom: !flags.keep_bom,
+            delimiter: flags.delimiter,
+            quote: flags.quote,
+            header_offset: flags.header_offset,
+            columns: flags.columns.unwrap_or_default(),
+            lenient: flags.lenient,
+            sheet: flags.sheet,
+        }
+    }
+}
+
+fn parse_columns(s: &str) -> Result<ColumnMapping, String> {
+    ColumnMapping::from_file(Path::new(s)).map_err(|e| e.to_string())
+}
+
+fn parse_char(s: &str) -> Result<char, String> {
+    match s {
+        "tab" | "\\t" | "\t" => Ok('\t'),
+        _ => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(c),
+                _ => Err(format!("expected a single character, got `{}`", s)),
+            }
+        },
+    }
+}
+
+/// Options of the xlsx output.
+#[derive(StructOpt, Debug)]
+struct XlsxFlags {
+    /// Write totals and percentages of the workbook as formulas
+    #[structopt(long)]
+    formulas: bool,
+}
+
+impl From<XlsxFlags> for XlsxOptions {
+    fn from(flags: XlsxFlags) -> Self {
+        XlsxOptions { formulas: flags.formulas }
+    }
+}
+
+fn parse_include(s: &str) -> Result<(String, String), String> {
+    let mut split = s.splitn(2, '=');
+    match (split.next().map(str::trim), split.next().map(str::trim)) {
+        (Some(cat), Some(val)) if !cat.is_empty() && !val.is_empty() => {
+            Ok((cat.to_string(), val.to_string()))
+        }
+        _ => Err(format!("expected `category=value`, got `{}`", s)),
+    }
+}
+
+fn run_job(path: &PathBuf, check: bool) -> Result<u128, RustlyzerError> {
+    let job = ReportJob::from_file(path)?;
+    if check {
+        println!("{:?} is valid", path);
+        return Ok(0);
+    }
+    job.run()
+}
+
+fn run_batch(
+    source: &PathBuf,
+    output_dir: Option<PathBuf>,
+    lang: Option<String>,
+    created_year: Option<u16>,
+    includes: Vec<(String, String)>,
+    csv: CsvOptions,
+    sheets: Vec<Sheet>,
+    xlsx: XlsxOptions,
+    theme: Option<PathBuf>,
+    threads: Option<usize>,
+) -> Result<u128, RustlyzerError> {
     let time = std::time::Instant::now();
-    let path_meta = "/home/faisal/dev/work/insight-tech/report-files/fail_test_01.json";
-    let path_input = "/home/faisal/dev/work/insight-tech/report-files/fail_test_01.csv";
-    let output_path = "/home/faisal/dev/work/insight-tech/report-files/fail_test_01.xlsx";
-    let lng = "ja".to_string();
-    let created_year = 2021 as u16;
-    let includes = vec![
-        // ("gender".to_string(), "男性".to_string()),
-        // ("children".to_string(), "2人".to_string()),
-    ];
-
-    match writer::create_output_file(&path_meta, &path_input, output_path, &lng, created_year, includes)
-    {
-        Ok(res) => (),//println!("{:?}", res),
-        Err(e) => {
-            // println!("{:?}", e);
-            println!("{}", e.to_string());
+    let jobs = if source.is_dir() {
+        let output_dir = output_dir.unwrap_or_else(|| source.to_owned());
+        let lang = lang.unwrap_or_else(|| "ja".to_string());
+        let created_year = created_year.unwrap_or(Utc::now().year() as u16);
+        let (jobs, unmatched) = batch::jobs_from_dir(source, &output_dir, &lang, created_year,
+                                                     includes)?;
+        for path in unmatched.iter() {
+            eprintln!("skipped {:?}: no matching json/csv pair", path);
         }
+        jobs.into_iter().map(|job| ReportJob {
+            csv: csv.clone(),
+            sheets: if sheets.is_empty() { job.sheets } else { sheets.clone() },
+            xlsx: xlsx.clone(),
+            theme: theme.clone(),
+            ..job
+        }).collect()
+    } else {
+        let given = [
+            ("--output-dir", output_dir.is_some()),
+            ("--lang", lang.is_some()),
+            ("--created-year", created_year.is_some()),
+            ("--include", !includes.is_empty()),
+            ("csv options", csv != CsvOptions::default()),
+            ("--sheets", !sheets.is_empty()),
+            ("--formulas", xlsx != XlsxOptions::default()),
+            ("--theme", theme.is_some()),
+        ].iter()
+            .filter(|(_, given)| *given)
+            .map(|(flag, _)| *flag)
+            .collect::<Vec<&str>>();
+        if !given.is_empty() {
+            return Err(RustlyzerError::InvalidConfigItemError(format!(
+                "{} only apply to a directory of surveys, jobs of the manifest {:?} set their own",
+                given.join(", "), source)));
+        }
+        BatchManifest::from_file(source)?.jobs
+    };
+
+    let summary = batch::run_batch(jobs, threads)?;
+    print!("{}", summary);
+    if !summary.is_success() {
+        return Err(RustlyzerError::InternalError(format!(
+            "{} of {} reports failed", summary.failed().len(), summary.outcomes.len())));
     }
-    let config = report_tool::ds::config::provide_include_criteria_constants();
-    // print!("{:#?}", config);
+    Ok(time.elapsed().as_millis())
+}
 
+fn serve(addr: &str, max_body_mib: u64, workers: usize) -> Result<u128, RustlyzerError> {
+    let server = ReportServer::bind(addr)?.with_max_body_len(max_body_mib * 1024 * 1024)
+        .with_workers(workers);
+    println!("Listening on http://{}", server.addr());
+    server.run();
+    Ok(0)
+}
 
-    let time = time.elapsed().as_millis();
-    println!("Finished processing in {} milliseconds", time);
+fn main() {
+    let res = match Command::from_args() {
+        Command::Generate { meta, input, csv, output, lang, created_year, includes, sheets, xlsx,
+            theme } => {
+            let created_year = created_year.unwrap_or(Utc::now().year() as u16);
+            let mut job = ReportJob::new(
+                &meta.to_string_lossy(),
+                &input.to_string_lossy(),
+                &output.to_string_lossy(),
+                &lang,
+                created_year,
+                includes,
+            );
+            if !sheets.is_empty() {
+                job.sheets = sheets;
+            }
+            job.csv = csv.into();
+            job.xlsx = xlsx.into();
+            job.theme = theme;
+            writer::create_output_file_from_job(&job)
+        }
+        Command::Job { job, check } => run_job(&job, check),
+        Command::Batch { source, output_dir, lang, created_year, includes, csv, sheets, xlsx,
+            theme, threads } => {
+            run_batch(&source, output_dir, lang, created_year, includes, csv.into(), sheets,
+                      xlsx.into(), theme, threads)
+        }
+        Command::Serve { addr, max_body_mib, workers } => serve(&addr, max_body_mib, workers),
+    };
 
+    if let Err(e) = res {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
 }
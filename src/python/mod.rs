@@ -0,0 +1,317 @@
+//! Python bindings, built with the `python` feature.
+//!
+//! ```python
+//! import report_tool
+//!
+//! config = report_tool.DataSetConfig("ja", 2021, [("gender", "男性")])
+//! ds = report_tool.DataSet.from_data(meta_json, config, csv_content)
+//! for table in ds.get_aggregate_tables():
+//!     print(table["meta"], table["table"]["rows"])
+//! ```
+//!
+//! Tables are returned as plain dicts (`headers`, `highlights`, `rows`, `footers`) so they can
+//! be passed straight to `pandas.DataFrame(t["rows"], columns=t["headers"])`. Cells are `str`,
+//! `int`, `float` (percentages in points), iso formatted dates or `None`.
+use crate::ds::table::{CellValue, SpecialCase, Table, TableWithMeta};
+use crate::ds::{ColumnMapping, CrosstabType, CsvOptions, DataSet, DataSetConfig, InputEncoding,
+                InputSheet, Language};
+use crate::errors::RustlyzerError;
+use crate::helpers::EnumAttrs;
+use crate::job::ReportJob;
+use crate::writer::{self, Sheet, XlsxOptions};
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use std::path::PathBuf;
+
+create_exception!(report_tool, RustlyzerException, PyException,
+    "Base class of every error raised by report_tool.");
+create_exception!(report_tool, MetadataWrongFormatError, RustlyzerException);
+create_exception!(report_tool, CsvInputWrongFormatError, RustlyzerException);
+create_exception!(report_tool, JsonInputWrongFormatError, RustlyzerException);
+create_exception!(report_tool, XlsxInputWrongFormatError, RustlyzerException);
+create_exception!(report_tool, JobWrongFormatError, RustlyzerException);
+create_exception!(report_tool, InvalidDataError, RustlyzerException);
+create_exception!(report_tool, InvalidConfigError, RustlyzerException);
+create_exception!(report_tool, ReportIoError, RustlyzerException);
+create_exception!(report_tool, XlsxError, RustlyzerException);
+create_exception!(report_tool, InternalError, RustlyzerException);
+
+/// Python wrapper of `DataSetConfig`.
+#[pyclass(name = "DataSetConfig")]
+#[derive(Clone)]
+pub struct PyDataSetConfig {
+    config: DataSetConfig,
+}
+
+#[pymethods]
+impl PyDataSetConfig {
+    /// DataSetConfig(language, created_year, includes=None)
+    ///
+    /// `includes` is a list of (category, value) tuples, e.g. [("gender", "男性")].
+    #[new]
+    fn new(language: String, created_year: u16, includes: Option<Vec<(String, String)>>)
+        -> PyResult<Self> {
+        let config = DataSetConfig::new_with_includes(language, created_year,
+                                                      includes.unwrap_or_default())?;
+        Ok(PyDataSetConfig { config })
+    }
+
+    #[getter]
+    fn language(&self) -> String {
+        self.config.lng.as_string(Language::En)
+    }
+
+    #[getter]
+    fn created_year(&self) -> u16 {
+        self.config.created_year
+    }
+}
+
+/// Python wrapper of `DataSet`.
+#[pyclass(name = "DataSet")]
+pub struct PyDataSet {
+    dataset: DataSet,
+}
+
+#[pymethods]
+impl PyDataSet {
+    /// DataSet.from_data(meta, config, content)
+    ///
+    /// `meta` is the metadata json and `content` the csv input, both as strings.
+    #[staticmethod]
+    fn from_data(meta: &str, config: &PyDataSetConfig, content: &str) -> PyResult<Self> {
+        let dataset = DataSet::from_data(meta, config.config.clone(), content)?;
+        Ok(PyDataSet { dataset })
+    }
+
+    /// DataSet.from_files(meta_path, config, input_path, encoding="auto", delimiter=",",
+    ///                   quote='"', header_offset=0, strip_bom=True, columns=None,
+    ///                   lenient=False, sheet=None)
+    ///
+    /// The csv input is streamed from its file, keeping only the records that pass the config.
+    /// `encoding` is "auto", "utf8" or "shift_jis" and `header_offset` the number of lines
+    /// before the header row. `columns` is the path of a json file mapping the fields to the
+    /// headers of the input. With `lenient` invalid rows are skipped, see `get_row_errors`.
+    /// `sheet` is the name or position of the worksheet read from an xlsx input, the first one
+    /// by default.
+    #[staticmethod]
+    #[pyo3(signature = (meta_path, config, input_path, encoding = "auto", delimiter = ',',
+                        quote = '"', header_offset = 0, strip_bom = true, columns = None,
+                        lenient = false, sheet = None))]
+    fn from_files(meta_path: PathBuf, config: &PyDataSetConfig, input_path: PathBuf,
+                  encoding: &str, delimiter: char, quote: char, header_offset: usize,
+                  strip_bom: bool, columns: Option<PathBuf>, lenient: bool,
+                  sheet: Option<&PyAny>) -> PyResult<Self> {
+        let options = csv_options(encoding, delimiter, quote, header_offset, strip_bom, columns,
+                                  lenient, sheet)?;
+        let dataset = DataSet::from_paths(&meta_path, config.config.clone(), &input_path,
+                                          &options)?;
+        Ok(PyDataSet { dataset })
+    }
+
+    fn __len__(&self) -> usize {
+        self.dataset.data.len()
+    }
+
+    fn get_fkc_raw_table(&self, py: Python) -> PyResult<PyObject> {
+        table_to_py(py, &self.dataset.get_fkc_raw_table()?)
+    }
+
+    fn get_it_raw_table(&self, py: Python) -> PyResult<PyObject> {
+        table_to_py(py, &self.dataset.get_it_raw_table()?)
+    }
+
+    fn get_user_graph_table_age1060(&self, py: Python) -> PyResult<PyObject> {
+        table_to_py(py, &self.dataset.get_user_graph_table_age1060()?)
+    }
+
+    fn get_user_graph_table_age1070(&self, py: Python) -> PyResult<PyObject> {
+        table_to_py(py, &self.dataset.get_user_graph_table_age1070()?)
+    }
+
+    fn get_user_graph_table_gender(&self, py: Python) -> PyResult<PyObject> {
+        table_to_py(py, &self.dataset.get_user_graph_table_gender()?)
+    }
+
+    fn get_user_graph_table_marital(&self, py: Python) -> PyResult<PyObject> {
+        table_to_py(py, &self.dataset.get_user_graph_table_marital()?)
+    }
+
+    fn get_user_graph_table_children(&self, py: Python) -> PyResult<PyObject> {
+        table_to_py(py, &self.dataset.get_user_graph_table_children()?)
+    }
+
+    fn get_user_graph_table_job(&self, py: Python) -> PyResult<PyObject> {
+        table_to_py(py, &self.dataset.get_user_graph_table_job()?)
+    }
+
+    fn get_user_graph_table_region(&self, py: Python) -> PyResult<PyObject> {
+        table_to_py(py, &self.dataset.get_user_graph_table_region()?)
+    }
+
+    fn get_user_graph_table_income(&self, py: Python) -> PyResult<PyObject> {
+        table_to_py(py, &self.dataset.get_user_graph_table_income()?)
+    }
+
+    fn get_aggregate_tables(&self, py: Python) -> PyResult<PyObject> {
+        tables_with_meta_to_py(py, &self.dataset.get_aggregate_tables()?)
+    }
+
+    /// get_crosstab_tables(crosstab_type="n")
+    ///
+    /// `crosstab_type` is "n" for counts or "perc" for percentages.
+    #[pyo3(signature = (crosstab_type = "n"))]
+    fn get_crosstab_tables(&self, py: Python, crosstab_type: &str) -> PyResult<PyObject> {
+        let crosstab_type = match crosstab_type {
+            "n" => CrosstabType::N,
+            "perc" => CrosstabType::Perc,
+            val => return Err(RustlyzerError::InvalidConfigValError {
+                config_item: "crosstab_type".to_string(),
+                val: val.to_string(),
+                expected_values: vec!["n".to_string(), "perc".to_string()],
+            }.into()),
+        };
+        tables_with_meta_to_py(py, &self.dataset.get_crosstab_tables(crosstab_type)?)
+    }
+
+    /// Rows skipped by a lenient read, with the row, column, value and reason of each.
+    fn get_row_errors(&self, py: Python) -> PyResult<PyObject> {
+        let (_, rows) = self.dataset.get_data_quality_tables();
+        table_to_py(py, &rows)
+    }
+}
+
+/// create_output_file(meta_path, input_path, output_path, lng, created_year, includes=None,
+///                    sheets=None, formulas=False, theme=None, encoding="auto",
+///                    delimiter=",", quote='"', header_offset=0, strip_bom=True, columns=None,
+///                    lenient=False, sheet=None)
+///
+/// Write the report and return the processing time in milliseconds. `sheets` lists the sheet
+/// ids to write, in order; every sheet is written by default. `formulas` writes the totals and
+/// percentages of a workbook as formulas. `theme` is the path of a theme json file. The input
+/// options are those of `DataSet.from_files`; a lenient read also writes the rejected rows next
+/// to the output and adds the data_quality sheet.
+#[pyfunction]
+#[pyo3(signature = (meta_path, input_path, output_path, lng, created_year, includes = None,
+                    sheets = None, formulas = false, theme = None, encoding = "auto",
+                    delimiter = ',', quote = '"', header_offset = 0, strip_bom = true,
+                    columns = None, lenient = false, sheet = None))]
+fn create_output_file(
+    meta_path: &str,
+    input_path: &str,
+    output_path: &str,
+    lng: &str,
+    created_year: u16,
+    includes: Option<Vec<(String, String)>>,
+    sheets: Option<Vec<String>>,
+    formulas: bool,
+    theme: Option<&str>,
+    encoding: &str,
+    delimiter: char,
+    quote: char,
+    header_offset: usize,
+    strip_bom: bool,
+    columns: Option<PathBuf>,
+    lenient: bool,
+    sheet: Option<&PyAny>,
+) -> PyResult<u128> {
+    let mut job = ReportJob::new(meta_path, input_path, output_path, lng, created_year,
+                                 includes.unwrap_or_default());
+    if let Some(sheets) = sheets {
+        job.sheets = sheets.iter()
+            .map(|id| id.parse::<Sheet>())
+            .collect::<Result<Vec<Sheet>, RustlyzerError>>()?;
+    }
+    job.xlsx = XlsxOptions { formulas };
+    job.theme = theme.map(PathBuf::from);
+    job.csv = csv_options(encoding, delimiter, quote, header_offset, strip_bom, columns, lenient,
+                          sheet)?;
+    Ok(writer::create_output_file_from_job(&job)?)
+}
+
+/// Input options from the keyword arguments shared by `from_files` and `create_output_file`.
+fn csv_options(encoding: &str, delimiter: char, quote: char, header_offset: usize,
+               strip_bom: bool, columns: Option<PathBuf>, lenient: bool, sheet: Option<&PyAny>)
+    -> PyResult<CsvOptions> {
+    let columns = match columns {
+        Some(path) => ColumnMapping::from_file(&path)?,
+        None => ColumnMapping::default(),
+    };
+    let sheet = match sheet {
+        Some(sheet) => match sheet.extract::<usize>() {
+            Ok(index) => InputSheet::Index(index),
+            Err(_) => InputSheet::Name(sheet.extract::<String>()?),
+        },
+        None => InputSheet::default(),
+    };
+    Ok(CsvOptions { encoding: encoding.parse::<InputEncoding>()?, strip_bom, delimiter, quote,
+        header_offset, columns, lenient, sheet })
+}
+
+fn table_to_py(py: Python, table: &Table) -> PyResult<PyObject> {
+    let row_count = table.cols.iter().map(|col| col.contents.len()).max().unwrap_or(0);
+    let rows = PyList::empty(py);
+    for i in 0..row_count {
+        let row = table.cols
+            .iter()
+            .map(|col| cell_to_py(py, col.contents.get(i).unwrap_or(&CellValue::Empty)))
+            .collect::<Vec<PyObject>>();
+        rows.append(row)?;
+    }
+    let dict = PyDict::new(py);
+    dict.set_item("headers", table.cols.iter().map(|col| &col.header.text).collect::<Vec<_>>())?;
+    dict.set_item("highlights",
+                  table.cols.iter().map(|col| col.header.highlight).collect::<Vec<_>>())?;
+    dict.set_item("rows", rows)?;
+    dict.set_item("footers", table.cols
+        .iter()
+        .map(|col| cell_to_py(py, col.footer.as_ref().unwrap_or(&CellValue::Empty)))
+        .collect::<Vec<PyObject>>())?;
+    Ok(dict.into())
+}
+
+fn cell_to_py(py: Python, value: &CellValue) -> PyObject {
+    match value {
+        CellValue::Text(text) => text.into_py(py),
+        CellValue::Integer(n) => n.into_py(py),
+        CellValue::Float(n) | CellValue::Percentage(n) => n.into_py(py),
+        CellValue::Date(date) => date.format("%Y-%m-%dT%H:%M:%S").to_string().into_py(py),
+        CellValue::Empty => py.None(),
+    }
+}
+
+fn tables_with_meta_to_py(py: Python, tables: &[TableWithMeta]) -> PyResult<PyObject> {
+    let list = PyList::empty(py);
+    for table_with_meta in tables.iter() {
+        let dict = PyDict::new(py);
+        dict.set_item("meta", &table_with_meta.meta)?;
+        dict.set_item("special_case", match table_with_meta.special_case {
+            SpecialCase::None => "none",
+            SpecialCase::SpaceAndHighlightOn4 => "space_and_highlight_on4",
+        })?;
+        dict.set_item("table", table_to_py(py, &table_with_meta.table)?)?;
+        list.append(dict)?;
+    }
+    Ok(list.into())
+}
+
+#[pymodule]
+fn report_tool(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyDataSetConfig>()?;
+    m.add_class::<PyDataSet>()?;
+    m.add_function(wrap_pyfunction!(create_output_file, m)?)?;
+    m.add("RustlyzerException", py.get_type::<RustlyzerException>())?;
+    m.add("MetadataWrongFormatError", py.get_type::<MetadataWrongFormatError>())?;
+    m.add("CsvInputWrongFormatError", py.get_type::<CsvInputWrongFormatError>())?;
+    m.add("JsonInputWrongFormatError", py.get_type::<JsonInputWrongFormatError>())?;
+    m.add("XlsxInputWrongFormatError", py.get_type::<XlsxInputWrongFormatError>())?;
+    m.add("JobWrongFormatError", py.get_type::<JobWrongFormatError>())?;
+    m.add("InvalidDataError", py.get_type::<InvalidDataError>())?;
+    m.add("InvalidConfigError", py.get_type::<InvalidConfigError>())?;
+    m.add("ReportIoError", py.get_type::<ReportIoError>())?;
+    m.add("XlsxError", py.get_type::<XlsxError>())?;
+    m.add("InternalError", py.get_type::<InternalError>())?;
+    Ok(())
+}
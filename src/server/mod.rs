@@ -0,0 +1,299 @@
+//! Local HTTP service generating reports on demand.
+//!
+//! - `POST /report` takes a `multipart/form-data` body with the parts `meta` (metadata json),
+//!   `csv` (survey responses, read as json, json lines or a workbook if its file name ends in
+//!   `.json`, `.ndjson`/`.jsonl` or `.xlsx`) and `config` (json [`ReportSettings`]) and responds
+//!   with the xlsx workbook.
+//! - `GET /include-criteria` responds with the json of `provide_include_criteria_constants`.
+//!
+//! Routes match the path of the request, whatever its query. Failures are answered with a json
+//! [`ErrorBody`] built from the `RustlyzerError`. A body larger than the limit of the server,
+//! 64 MiB unless set with `with_max_body_len`, is refused with status 413. Requests are handled
+//! by a fixed number of worker threads, 4 unless set with `with_workers`; the others wait.
+use crate::ds::config::provide_include_criteria_constants;
+use crate::ds::{CsvOptions, DataSet, DataSetConfig, InputFormat};
+use crate::errors::RustlyzerError;
+use crate::job::{default_lang, include_tuples, validate_lang_and_sheets};
+use crate::writer::{self, Sheet, Theme, XlsxOptions};
+use multipart::server::Multipart;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+use std::net::SocketAddr;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::Arc;
+use tiny_http::{Header, Method, Request, Response};
+
+const XLSX_CONTENT_TYPE: &'static str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+const JSON_CONTENT_TYPE: &'static str = "application/json; charset=utf-8";
+const DEFAULT_MAX_BODY_LEN: u64 = 64 * 1024 * 1024;
+/// Requests handled at once, each holding a dataset and a workbook in memory
+const DEFAULT_WORKERS: usize = 4;
+
+/// Report options sent in the `config` part of a `POST /report` request.
+///
+/// ```json
+/// {"lang": "ja", "created_year": 2021, "includes": {"gender": ["男性"]}}
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ReportSettings {
+    /// Language for data representation ("en" or "ja")
+    #[serde(default = "default_lang")]
+    pub lang: String,
+    /// Year the data was collected
+    pub created_year: u16,
+    /// Include criteria, as category -> accepted values
+    #[serde(default)]
+    pub includes: BTreeMap<String, Vec<String>>,
+    /// Dialect of the `csv` part
+    #[serde(default)]
+    pub csv: CsvOptions,
+    /// Sheets to produce
+    #[serde(default = "Sheet::get_all")]
+    pub sheets: Vec<Sheet>,
+    /// Options of the xlsx output
+    #[serde(default)]
+    pub xlsx: XlsxOptions,
+    /// Theme of the workbook
+    #[serde(default)]
+    pub theme: Theme,
+}
+
+impl ReportSettings {
+    pub fn config(&self) -> Result<DataSetConfig, RustlyzerError> {
+        validate_lang_and_sheets(&self.lang, &self.sheets, RustlyzerError::RequestWrongFormat)?;
+        DataSetConfig::new_with_includes(self.lang.to_owned(), self.created_year,
+                                         include_tuples(&self.includes))
+    }
+}
+
+/// Json body of an error response.
+///
+/// `kind` is the `RustlyzerError` variant name, `detail` its serialized fields.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ErrorBody {
+    pub kind: String,
+    pub message: String,
+    pub detail: serde_json::Value,
+}
+
+impl From<&RustlyzerError> for ErrorBody {
+    fn from(err: &RustlyzerError) -> Self {
+        let detail = serde_json::to_value(err).unwrap_or(serde_json::Value::Null);
+        // Unit variants serialize to their name, the others to `{ "<name>": <fields> }`.
+        let (kind, detail) = match detail {
+            serde_json::Value::String(name) => (name, serde_json::Value::Null),
+            serde_json::Value::Object(map) => map
+                .into_iter()
+                .next()
+                .unwrap_or(("InternalError".to_string(), serde_json::Value::Null)),
+            other => ("InternalError".to_string(), other),
+        };
+        ErrorBody { kind, message: err.to_string(), detail }
+    }
+}
+
+/// Http status matching an error: 400 for bad input, 413 for a body over the limit, 500
+/// otherwise.
+fn status_code_of(err: &RustlyzerError) -> u16 {
+    match err {
+        RustlyzerError::RequestTooLarge(_) => 413,
+        RustlyzerError::MetadataWrongFormat(_)
+        | RustlyzerError::CsvInputWrongFormat(_)
+        | RustlyzerError::JsonInputWrongFormat(_)
+        | RustlyzerError::XlsxInputWrongFormat(_)
+        | RustlyzerError::RequestWrongFormat(_)
+        | RustlyzerError::JobWrongFormat(_)
+        | RustlyzerError::ThemeWrongFormat(_)
+        | RustlyzerError::InvalidDataError { .. }
+        | RustlyzerError::InvalidConfigValError { .. }
+        | RustlyzerError::InvalidConfigItemError(_) => 400,
+        _ => 500,
+    }
+}
+
+/// Report service bound to a local address.
+pub struct ReportServer {
+    server: Arc<tiny_http::Server>,
+    max_body_len: u64,
+    workers: usize,
+}
+
+impl ReportServer {
+    /// Bind to `addr`, e.g. `127.0.0.1:8080`. Port 0 picks a free port, see `addr`.
+    pub fn bind(addr: &str) -> Result<Self, RustlyzerError> {
+        let server = tiny_http::Server::http(addr)
+            .map_err(|e| RustlyzerError::IoError(e.to_string()))?;
+        Ok(ReportServer { server: Arc::new(server), max_body_len: DEFAULT_MAX_BODY_LEN,
+                          workers: DEFAULT_WORKERS })
+    }
+
+    /// Refuse request bodies larger than `max_body_len` bytes.
+    pub fn with_max_body_len(mut self, max_body_len: u64) -> Self {
+        self.max_body_len = max_body_len;
+        self
+    }
+
+    /// Handle at most `workers` requests at once, at least one.
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.server.server_addr()
+    }
+
+    /// Serve requests until the process exits, on `workers` threads taking them in turn.
+    pub fn run(&self) {
+        let max_body_len = self.max_body_len;
+        let workers = (0..self.workers)
+            .map(|_| {
+                let server = Arc::clone(&self.server);
+                std::thread::spawn(move || loop {
+                    match server.recv() {
+                        // A panicking request must not take its worker down with it
+                        Ok(request) => if panic::catch_unwind(
+                            AssertUnwindSafe(|| handle(request, max_body_len))).is_err() {
+                            eprintln!("request handler panicked");
+                        },
+                        Err(e) => eprintln!("failed to receive request: {}", e),
+                    }
+                })
+            })
+            .collect::<Vec<std::thread::JoinHandle<()>>>();
+        for worker in workers {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn handle(mut request: Request, max_body_len: u64) {
+    let url = request.url().to_string();
+    let path = url.split('?').next().unwrap_or_default();
+    let response = match (request.method(), path) {
+        (Method::Post, "/report") => generate_report(&mut request, max_body_len)
+            .map(|xlsx| response(200, XLSX_CONTENT_TYPE, xlsx)),
+        (Method::Get, "/include-criteria") => serde_json::to_vec(
+            &provide_include_criteria_constants())
+            .map(|json| response(200, JSON_CONTENT_TYPE, json))
+            .map_err(|e| RustlyzerError::InternalError(e.to_string())),
+        (method, _) => Ok(error_response(404, &RustlyzerError::RequestWrongFormat(
+            format!("no route for {} {}", method, url)))),
+    };
+    let response = response.unwrap_or_else(|err| error_response(status_code_of(&err), &err));
+    if let Err(e) = request.respond(response) {
+        eprintln!("failed to send response: {}", e);
+    }
+}
+
+fn error_response(code: u16, err: &RustlyzerError) -> Response<std::io::Cursor<Vec<u8>>> {
+    let body = serde_json::to_vec(&ErrorBody::from(err)).unwrap_or_default();
+    response(code, JSON_CONTENT_TYPE, body)
+}
+
+fn response(code: u16, content_type: &str, body: Vec<u8>) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_data(body)
+        .with_status_code(code)
+        .with_header(Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()).unwrap())
+}
+
+/// Reader failing once more than `max_len` bytes were read, noting it in `exceeded`.
+struct LimitedReader<'e, R> {
+    inner: R,
+    remaining: u64,
+    max_len: u64,
+    exceeded: &'e Cell<bool>,
+}
+
+impl<'e, R: Read> Read for LimitedReader<'e, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // One byte past the limit tells a body over it from one just at it
+        let len = buf.len().min(self.remaining.saturating_add(1) as usize);
+        let n = self.inner.read(&mut buf[..len])?;
+        if n as u64 > self.remaining {
+            self.exceeded.set(true);
+            return Err(io::Error::new(io::ErrorKind::Other, format!(
+                "body larger than {} bytes", self.max_len)));
+        }
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+fn generate_report(request: &mut Request, max_body_len: u64)
+    -> Result<Vec<u8>, RustlyzerError> {
+    if request.body_length().map_or(false, |len| len as u64 > max_body_len) {
+        return Err(RustlyzerError::RequestTooLarge(max_body_len));
+    }
+    let boundary = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Content-Type"))
+        .and_then(|h| {
+            let value = h.value.as_str();
+            if !value.starts_with("multipart/form-data") {
+                return None;
+            }
+            value.split(';')
+                .filter_map(|param| param.trim().strip_prefix("boundary="))
+                .map(|b| b.trim_matches('"').to_string())
+                .next()
+        })
+        .ok_or_else(|| RustlyzerError::RequestWrongFormat(
+            "expected a multipart/form-data body".to_string()))?;
+
+    let mut meta = None;
+    let mut csv = None;
+    let mut settings = None;
+    let exceeded = Cell::new(false);
+    let body = LimitedReader { inner: request.as_reader(), remaining: max_body_len,
+                               max_len: max_body_len, exceeded: &exceeded };
+    // A body cut short by the limit fails to parse too
+    let body_error = |e: io::Error| if exceeded.get() {
+        RustlyzerError::RequestTooLarge(max_body_len)
+    } else {
+        RustlyzerError::RequestWrongFormat(format!("multipart body: {}", e))
+    };
+    let mut multipart = Multipart::with_body(body, boundary);
+    while let Some(mut field) = multipart.read_entry().map_err(body_error)? {
+        let mut content = Vec::<u8>::new();
+        field.data.read_to_end(&mut content).map_err(body_error)?;
+        // The csv is decoded as its settings say, the other parts are utf-8
+        let text = |content: Vec<u8>| String::from_utf8(content).map_err(|e|
+            RustlyzerError::RequestWrongFormat(
+                format!("part `{}` is not valid utf-8: {}", field.headers.name, e)));
+        match &*field.headers.name {
+            "meta" => meta = Some((text(content)?, field.headers.filename.clone())),
+            "csv" => csv = Some((content, field.headers.filename.clone())),
+            "config" => settings = Some(serde_json::from_str::<ReportSettings>(&text(content)?)
+                .map_err(|e| RustlyzerError::RequestWrongFormat(
+                    format!("part `config`: {}", e)))?),
+            name => return Err(RustlyzerError::RequestWrongFormat(
+                format!("unexpected part `{}`", name))),
+        }
+    }
+    let missing = |name: &str| RustlyzerError::RequestWrongFormat(
+        format!("missing part `{}`", name));
+    let (meta, meta_name) = meta.ok_or_else(|| missing("meta"))?;
+    let (csv, csv_name) = csv.ok_or_else(|| missing("csv"))?;
+    let settings = settings.ok_or_else(|| missing("config"))?;
+
+    let format = csv_name.as_deref().map_or(InputFormat::Csv,
+                                            |name| InputFormat::from_path(Path::new(name)));
+    let mut dataset = DataSet::from_reader_with_format(&meta, settings.config()?, csv.as_slice(),
+                                                       format, &settings.csv)?;
+    // File names of the uploads, for the report-info sheet
+    dataset.provenance.meta.name = meta_name;
+    dataset.provenance.input.name = csv_name;
+    // Rows left out by a lenient read are listed in the workbook
+    let mut sheets = settings.sheets.clone();
+    if settings.csv.lenient && !sheets.contains(&Sheet::DataQuality) {
+        sheets.push(Sheet::DataQuality);
+    }
+    writer::write_output_bytes(&dataset, &sheets, &settings.xlsx, &settings.theme)
+}
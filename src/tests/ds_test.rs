@@ -12,3 +12,359 @@ fn meta_custom_fields_length() {
     // 23 in total, 2 html (ignored)
     assert_eq!(ds.meta.custom_fields.len(), 21);
 }
+
+#[test]
+fn report_info_records_inputs_and_counts() {
+    use crate::ds::provenance::SourceFile;
+    use crate::ds::table::CellValue;
+    use std::path::Path;
+
+    assert_eq!(SourceFile::new("abc").sha256,
+               "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    let mut meta = String::new();
+    let mut data = String::new();
+    File::open("./src/tests/meta_test.json").unwrap().read_to_string(&mut meta).unwrap();
+    File::open("./src/tests/input_test.csv").unwrap().read_to_string(&mut data).unwrap();
+    let config = DataSetConfig::new_with_includes("ja".to_string(), 2020,
+        vec![("gender".to_string(), "男性".to_string())]).unwrap();
+    let ds = DataSet::from_data(&meta, config, &data).unwrap()
+        .with_source_paths(Path::new("/in/meta_test.json"), Path::new("input_test.csv"));
+    assert_eq!(ds.provenance.raw_records, 300);
+    assert!(ds.data.len() < 300);
+    assert_eq!(ds.provenance.meta.name.as_deref(), Some("meta_test.json"));
+
+    let generated_at = chrono::NaiveDate::from_ymd(2021, 4, 1).and_hms(9, 30, 0);
+    let table = ds.get_report_info_table(generated_at);
+    let rows = table.cols[0].contents.iter().zip(table.cols[1].contents.iter())
+        .map(|(key, value)| (key.to_string(), value.clone()))
+        .collect::<Vec<(String, CellValue)>>();
+    let value = |key: &str| rows.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone()).unwrap();
+    assert_eq!(value("入力ファイル"), CellValue::Text("input_test.csv".to_string()));
+    assert_eq!(value("入力ファイル SHA-256"), CellValue::Text(ds.provenance.input.sha256.clone()));
+    assert_eq!(value("作成年"), CellValue::Integer(2020));
+    assert_eq!(value("抽出条件"), CellValue::Text("性別: 男性".to_string()));
+    assert_eq!(value("入力件数"), CellValue::Integer(300));
+    assert_eq!(value("集計件数"), CellValue::Integer(ds.data.len() as i64));
+    assert_eq!(value("作成日時"), CellValue::Date(generated_at));
+}
+
+#[test]
+fn dataset_streamed_from_reader() {
+    use crate::ds::CsvOptions;
+    use std::path::Path;
+
+    // Reader handing out a few bytes at a time
+    struct Trickle<R>(R);
+    impl<R: Read> Read for Trickle<R> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let len = buf.len().min(7);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    let mut meta = String::new();
+    File::open("./src/tests/meta_test.json").unwrap().read_to_string(&mut meta).unwrap();
+    let config = || DataSetConfig::new_with_includes("ja".to_string(), 2020,
+        vec![("gender".to_string(), "女性".to_string())]).unwrap();
+    let input = File::open("./src/tests/input_test.csv").unwrap();
+    let streamed = DataSet::from_reader(&meta, config(), Trickle(input), &CsvOptions::default())
+        .unwrap();
+    let loaded = DataSet::from_paths(Path::new("./src/tests/meta_test.json"), config(),
+                                     Path::new("./src/tests/input_test.csv"),
+                                     &CsvOptions::default()).unwrap();
+    assert_eq!(streamed.provenance.raw_records, 300);
+    assert_eq!(streamed.data.len(), loaded.data.len());
+    assert!(streamed.data.len() < 300);
+    assert_eq!(streamed.provenance.input.sha256,
+               "66d1c0520aa07e2e16ecd7b9931fa4c97e370459920e9bc56e07b4b950d8cb13");
+    assert_eq!(loaded.provenance.input.sha256, streamed.provenance.input.sha256);
+    assert_eq!(loaded.provenance.input.name.as_deref(), Some("input_test.csv"));
+    assert_eq!(streamed.get_it_raw_table().unwrap().cols[0].contents,
+               loaded.get_it_raw_table().unwrap().cols[0].contents);
+}
+
+#[test]
+fn csv_dialects_read_the_same_records() {
+    use crate::ds::{CsvOptions, InputEncoding};
+    use crate::errors::RustlyzerError;
+    use encoding_rs::SHIFT_JIS;
+
+    let mut meta = String::new();
+    let mut content = String::new();
+    File::open("./src/tests/meta_test.json").unwrap().read_to_string(&mut meta).unwrap();
+    File::open("./src/tests/input_test.csv").unwrap().read_to_string(&mut content).unwrap();
+    let config = || DataSetConfig::new("ja".to_string(), 2020).unwrap();
+    // Ids and genders, free text may have characters Shift_JIS lacks
+    let ids = |ds: &DataSet| ds.get_it_raw_table().unwrap().cols.iter()
+        .filter(|col| col.header.text == "投稿id" || col.header.text == "性別")
+        .map(|col| col.contents.clone())
+        .collect::<Vec<Vec<crate::ds::table::CellValue>>>();
+    let expected = ids(&DataSet::from_data(&meta, config(), &content).unwrap());
+
+    // Excel export: Shift_JIS, tab separated, single quotes, a title line above the header
+    let mut tsv = csv::WriterBuilder::new().delimiter(b'\t').quote(b'\'')
+        .from_writer(b"survey export\n".to_vec());
+    let mut rdr = csv::ReaderBuilder::new().has_headers(false).from_reader(content.as_bytes());
+    for record in rdr.records() {
+        tsv.write_record(&record.unwrap()).unwrap();
+    }
+    let tsv = String::from_utf8(tsv.into_inner().unwrap()).unwrap();
+    let (sjis, _, _) = SHIFT_JIS.encode(&tsv);
+    let options = CsvOptions { delimiter: '\t', quote: '\'', header_offset: 1,
+        ..CsvOptions::default() };
+    let ds = DataSet::from_reader(&meta, config(), &*sjis, &options).unwrap();
+    assert_eq!(ids(&ds), expected);
+
+    // UTF-8 with a BOM
+    let with_bom = format!("\u{feff}{}", content);
+    assert_eq!(ids(&DataSet::from_data(&meta, config(), &with_bom).unwrap()), expected);
+
+    // An invalid byte on the third line
+    let mut bytes = content.clone().into_bytes();
+    let third_line = bytes.iter().enumerate().filter(|(_, &b)| b == b'\n').nth(1).unwrap().0 + 1;
+    bytes.insert(third_line, 0xff);
+    let options = CsvOptions { encoding: InputEncoding::Utf8, ..CsvOptions::default() };
+    match DataSet::from_reader(&meta, config(), bytes.as_slice(), &options) {
+        Err(RustlyzerError::CsvInputWrongFormat(msg)) => {
+            assert!(msg.starts_with("line 3:"), "{}", msg)
+        },
+        other => panic!("expected a decoding error, got {:?}", other.map(|ds| ds.data.len())),
+    }
+}
+
+#[test]
+fn columns_mapped_from_source_headers() {
+    use crate::ds::{ColumnMapping, CsvOptions};
+    use crate::errors::RustlyzerError;
+
+    let mut meta = String::new();
+    let mut content = String::new();
+    File::open("./src/tests/meta_test.json").unwrap().read_to_string(&mut meta).unwrap();
+    File::open("./src/tests/input_test.csv").unwrap().read_to_string(&mut content).unwrap();
+    let config = || DataSetConfig::new("ja".to_string(), 2020).unwrap();
+    let expected = DataSet::from_data(&meta, config(), &content).unwrap();
+
+    let (header, records) = content.split_at(content.find('\n').unwrap());
+    let header = header.replace("updated at", "更新日時").replace("gender", "性別")
+        .replace("field1,", "Q1 住所,").replace("field2,", "Q2,");
+    let renamed = format!("{}{}", header, records);
+    let mut columns = ColumnMapping::from_json(r#"{
+        "updated_at": "更新日時",
+        "gender": "性別",
+        "custom_fields": { "Q1 住所": 1, "Q2": 2 }
+    }"#).unwrap();
+    let options = CsvOptions { columns: columns.clone(), ..CsvOptions::default() };
+    let ds = DataSet::from_reader(&meta, config(), renamed.as_bytes(), &options).unwrap();
+    assert_eq!(ds.data.len(), expected.data.len());
+    let col = |ds: &DataSet, title: &str| ds.get_fkc_raw_table().unwrap().cols.into_iter()
+        .find(|col| col.header.text == title).unwrap().contents;
+    assert_eq!(col(&ds, "性別"), col(&expected, "性別"));
+    // Custom fields other than the mapped ones are left out
+    let field = |ds: &DataSet, id: usize| ds.data.records[0].custom_fields.get(&id).cloned();
+    assert_eq!(field(&ds, 1), field(&expected, 1));
+    assert_eq!(field(&ds, 3), Some(None));
+    assert!(field(&expected, 3).unwrap().is_some());
+
+    columns.birth_year = "生年".to_string();
+    columns.custom_fields.insert("Q9".to_string(), 9);
+    let options = CsvOptions { columns, ..CsvOptions::default() };
+    match DataSet::from_reader(&meta, config(), renamed.as_bytes(), &options) {
+        Err(RustlyzerError::CsvInputWrongFormat(msg)) => {
+            assert_eq!(msg, "missing columns `生年` (birth_year), `Q9` (field9)")
+        },
+        other => panic!("expected missing columns, got {:?}", other.map(|ds| ds.data.len())),
+    }
+}
+
+#[test]
+fn lenient_read_rejects_invalid_rows() {
+    use crate::ds::CsvOptions;
+    use crate::ds::table::CellValue;
+    use crate::errors::RustlyzerError;
+
+    let mut meta = String::new();
+    let mut content = String::new();
+    File::open("./src/tests/meta_test.json").unwrap().read_to_string(&mut meta).unwrap();
+    File::open("./src/tests/input_test.csv").unwrap().read_to_string(&mut content).unwrap();
+    let config = || DataSetConfig::new("ja".to_string(), 2020).unwrap();
+    let expected = DataSet::from_data(&meta, config(), &content).unwrap();
+
+    // Rows 3, 5 and 7: an unknown gender, a birth year after 2020 and a missing field
+    let mut rdr = csv::ReaderBuilder::new().has_headers(false).flexible(true)
+        .from_reader(content.as_bytes());
+    let mut records = rdr.records().map(|r| r.unwrap()).collect::<Vec<csv::StringRecord>>();
+    let header = records[0].clone();
+    let set = |record: &mut csv::StringRecord, name: &str, val: &str| {
+        *record = record.iter().zip(header.iter())
+            .map(|(v, h)| if h == name { val } else { v })
+            .collect::<csv::StringRecord>();
+    };
+    set(&mut records[2], "gender", "不明");
+    set(&mut records[4], "birth_year", "2090");
+    let len = records[6].len();
+    records[6].truncate(len - 1);
+    let mut wtr = csv::WriterBuilder::new().flexible(true).from_writer(Vec::<u8>::new());
+    for record in records.iter() {
+        wtr.write_record(record).unwrap();
+    }
+    let invalid = wtr.into_inner().unwrap();
+
+    match DataSet::from_reader(&meta, config(), invalid.as_slice(), &CsvOptions::default()) {
+        Err(RustlyzerError::CsvInputWrongFormat(_)) => (),
+        other => panic!("expected a strict read to fail, got {:?}", other.map(|ds| ds.data.len())),
+    }
+    let options = CsvOptions { lenient: true, ..CsvOptions::default() };
+    let ds = DataSet::from_reader(&meta, config(), invalid.as_slice(), &options).unwrap();
+    assert_eq!(ds.data.len(), expected.data.len() - 3);
+    assert_eq!(ds.provenance.raw_records, expected.provenance.raw_records);
+    let errors = ds.data_quality.row_errors.iter()
+        .map(|e| (e.row, e.column.as_str(), e.value.as_str()))
+        .collect::<Vec<(usize, &str, &str)>>();
+    assert_eq!(errors, vec![(3, "gender", "不明"), (5, "birth_year", "2090"), (7, "", "")]);
+    assert_eq!(ds.data_quality.row_errors[2].reason, "38 fields, expected 39");
+
+    let (summary, rows) = ds.get_data_quality_tables();
+    assert_eq!(summary.cols[0].contents, vec![CellValue::Text("gender".to_string()),
+        CellValue::Text("birth_year".to_string()), CellValue::Text("不明".to_string())]);
+    assert_eq!(summary.cols[1].footer, Some(CellValue::Integer(3)));
+    assert_eq!(rows.cols[0].contents.len(), 3);
+
+    let mut rejected = Vec::<u8>::new();
+    ds.data_quality.write_csv(&mut rejected).unwrap();
+    let mut rdr = csv::ReaderBuilder::new().flexible(true).from_reader(rejected.as_slice());
+    assert_eq!(rdr.headers().unwrap().get(4), Some("id"));
+    let rows = rdr.records().map(|r| r.unwrap()).collect::<Vec<csv::StringRecord>>();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[1].get(0), Some("5"));
+    assert_eq!(rows[1].iter().skip(4).collect::<csv::StringRecord>(), records[4]);
+}
+
+#[test]
+fn json_input_reads_the_same_records() {
+    use crate::ds::{CsvOptions, InputFormat};
+    use serde_json::{Map, Value};
+    use std::path::Path;
+
+    let mut meta = String::new();
+    let mut content = String::new();
+    File::open("./src/tests/meta_test.json").unwrap().read_to_string(&mut meta).unwrap();
+    File::open("./src/tests/input_test.csv").unwrap().read_to_string(&mut content).unwrap();
+    let config = || DataSetConfig::new("ja".to_string(), 2020).unwrap();
+    let expected = DataSet::from_data(&meta, config(), &content).unwrap();
+
+    // Records as exported in json: numbers, and multiselect answers as arrays
+    let numbers = ["price", "bonus_point", "birth_year", "children", "household_income_min",
+        "household_income_max"];
+    let mut rdr = csv::Reader::from_reader(content.as_bytes());
+    let headers = rdr.headers().unwrap().clone();
+    let records = rdr.records().map(|record| {
+        let record = record.unwrap();
+        headers.iter().zip(record.iter()).map(|(header, value)| {
+            let value = match serde_json::from_str::<Value>(value) {
+                Ok(parsed) if numbers.contains(&header) || header.starts_with("field") => parsed,
+                _ => Value::String(value.to_string()),
+            };
+            (header.to_string(), value)
+        }).collect::<Map<String, Value>>()
+    }).map(Value::Object).collect::<Vec<Value>>();
+    assert!(records.iter().any(|r| r.as_object().unwrap().values().any(|v| v.is_array())));
+
+    let raw = |ds: &DataSet| ds.get_fkc_raw_table().unwrap().cols.into_iter()
+        .map(|col| col.contents)
+        .collect::<Vec<Vec<crate::ds::table::CellValue>>>();
+    let array = serde_json::to_string(&records).unwrap();
+    let ds = DataSet::from_reader_with_format(&meta, config(), array.as_bytes(), InputFormat::Json,
+                                              &CsvOptions::default()).unwrap();
+    assert_eq!(ds.data.len(), expected.data.len());
+    assert_eq!(raw(&ds), raw(&expected));
+
+    let mut lines = records.iter().map(|r| r.to_string()).collect::<Vec<String>>();
+    lines.insert(1, String::new());
+    let ndjson = lines.join("\n");
+    let ds = DataSet::from_reader_with_format(&meta, config(), ndjson.as_bytes(),
+                                              InputFormat::Ndjson, &CsvOptions::default())
+        .unwrap();
+    assert_eq!(raw(&ds), raw(&expected));
+
+    // Lines 3 and 4: not json, an unknown gender
+    lines[2] = "{\"id\": ".to_string();
+    let mut record = records[2].clone();
+    record["gender"] = Value::String("不明".to_string());
+    lines[3] = record.to_string();
+    let ndjson = lines.join("\n");
+    let read = |options: &CsvOptions| DataSet::from_reader_with_format(
+        &meta, config(), ndjson.as_bytes(), InputFormat::Ndjson, options);
+    assert!(read(&CsvOptions::default()).is_err());
+    let ds = read(&CsvOptions { lenient: true, ..CsvOptions::default() }).unwrap();
+    assert_eq!(ds.data.len(), expected.data.len() - 2);
+    let errors = ds.data_quality.row_errors.iter()
+        .map(|e| (e.row, e.column.as_str(), e.value.as_str()))
+        .collect::<Vec<(usize, &str, &str)>>();
+    assert_eq!(errors, vec![(3, "", ""), (4, "gender", "不明")]);
+
+    assert_eq!(InputFormat::from_path(Path::new("survey.JSONL")), InputFormat::Ndjson);
+    assert_eq!(InputFormat::from_path(Path::new("survey.json")), InputFormat::Json);
+    assert_eq!(InputFormat::from_path(Path::new("survey.tsv")), InputFormat::Csv);
+}
+
+#[test]
+fn xlsx_input_reads_the_same_records() {
+    use crate::ds::{CsvOptions, InputFormat, InputSheet};
+    use crate::errors::RustlyzerError;
+    use std::path::Path;
+
+    let mut meta = String::new();
+    let mut content = String::new();
+    File::open("./src/tests/meta_test.json").unwrap().read_to_string(&mut meta).unwrap();
+    File::open("./src/tests/input_test.csv").unwrap().read_to_string(&mut content).unwrap();
+    let config = || DataSetConfig::new("ja".to_string(), 2020).unwrap();
+    let expected = DataSet::from_data(&meta, config(), &content).unwrap();
+    let raw = |ds: &DataSet| ds.get_fkc_raw_table().unwrap().cols.into_iter()
+        .map(|col| col.contents)
+        .collect::<Vec<Vec<crate::ds::table::CellValue>>>();
+    assert_eq!(InputFormat::from_path(Path::new("回答.xlsx")), InputFormat::Xlsx);
+
+    // Responses on the second sheet, under a title row
+    let read = |sheet: InputSheet| {
+        let options = CsvOptions { sheet, header_offset: 1, ..Default::default() };
+        let input = File::open("./src/tests/input_test.xlsx").unwrap();
+        DataSet::from_reader_with_format(&meta, config(), input, InputFormat::Xlsx, &options)
+    };
+    let ds = read(InputSheet::Name("回答".to_string())).unwrap();
+    assert_eq!(ds.data.len(), expected.data.len());
+    assert_eq!(raw(&ds), raw(&expected));
+    let ds = read(InputSheet::Index(1)).unwrap();
+    assert_eq!(raw(&ds), raw(&expected));
+
+    match read(InputSheet::Name("Sheet1".to_string())) {
+        Err(RustlyzerError::InvalidConfigValError { config_item, expected_values, .. }) => {
+            assert_eq!(config_item, "sheet");
+            assert_eq!(expected_values, vec!["表紙".to_string(), "回答".to_string()]);
+        },
+        _ => panic!("unknown sheet read"),
+    }
+}
+
+#[test]
+fn sha256_of_chunked_and_multi_block_input() {
+    use crate::helpers::sha256::{sha256_hex, HashingReader};
+
+    assert_eq!(sha256_hex(b""),
+               "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    // Padding that fits the last block, that needs a block of its own, and a full last block
+    let expected = [
+        (55, "9f4390f8d30c2dd92ec9f095b65e2b9ae9b0a925a5258e241c9f1e910f734318"),
+        (56, "b35439a4ac6f0948b6d6f9e3c6af0f5f590ce20f1bde7090ef7970686ec6738a"),
+        (64, "ffe054fe7ae0cb6dc65c3af9b61d5209f439851db43d0ba5997337df154668eb"),
+        (1000, "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"),
+    ];
+    for &(len, digest) in expected.iter() {
+        let content = vec![b'a'; len];
+        assert_eq!(sha256_hex(&content), digest);
+        // Read 7 bytes at a time
+        let mut reader = HashingReader::new(content.as_slice());
+        let mut buf = [0u8; 7];
+        while reader.read(&mut buf).unwrap() > 0 {}
+        assert_eq!(reader.finish_hex(), digest);
+    }
+}
@@ -0,0 +1,75 @@
+/* Smoke test for the C API, compiled and run by src/tests/ffi_test.rs.
+ *
+ * Usage: ffi_smoke <meta.json> <input.csv> <output.xlsx>
+ */
+#include <stdio.h>
+#include <string.h>
+#include "report_tool.h"
+
+static int check(ReportToolStatus status, char *err, const char *what) {
+    if (status != REPORT_TOOL_STATUS_OK) {
+        fprintf(stderr, "%s failed with status %d: %s\n", what, (int)status, err ? err : "");
+        report_tool_string_free(err);
+        return 1;
+    }
+    return 0;
+}
+
+int main(int argc, char **argv) {
+    char *err = NULL;
+    char *json = NULL;
+    ReportToolDataSet *ds = NULL;
+
+    if (argc != 4) {
+        fprintf(stderr, "usage: %s <meta.json> <input.csv> <output.xlsx>\n", argv[0]);
+        return 2;
+    }
+
+    if (check(report_tool_include_criteria_constants(&json, &err), err, "constants")) return 1;
+    if (strstr(json, "\"gender\"") == NULL) {
+        fprintf(stderr, "unexpected constants: %s\n", json);
+        return 1;
+    }
+    report_tool_string_free(json);
+
+    if (check(report_tool_dataset_from_files(argv[1], argv[2], "ja", 2020, NULL, NULL, 0, &ds,
+                                             &err), err, "dataset")) return 1;
+    if (report_tool_dataset_len(ds) == 0) {
+        fprintf(stderr, "dataset is empty\n");
+        return 1;
+    }
+    if (check(report_tool_write_workbook(ds, argv[3], &err), err, "workbook")) return 1;
+
+    uint8_t *bytes = NULL;
+    size_t bytes_len = 0;
+    if (check(report_tool_write_workbook_bytes(ds, &bytes, &bytes_len, &err), err,
+              "workbook bytes")) return 1;
+    if (bytes == NULL || bytes_len == 0) {
+        fprintf(stderr, "workbook buffer is empty\n");
+        return 1;
+    }
+    report_tool_bytes_free(bytes, bytes_len);
+    report_tool_dataset_free(ds);
+    ds = NULL;
+
+    /* Errors come back as a status code plus a message */
+    const char *categories[] = { "gender" };
+    const char *values[] = { "unknown" };
+    ReportToolStatus status = report_tool_dataset_from_files(argv[1], argv[2], "ja", 2020,
+                                                             categories, values, 1, &ds, &err);
+    if (status != REPORT_TOOL_STATUS_INVALID_CONFIG || err == NULL || ds != NULL) {
+        fprintf(stderr, "expected invalid config, got status %d\n", (int)status);
+        return 1;
+    }
+    report_tool_string_free(err);
+
+    status = report_tool_write_workbook(NULL, argv[3], &err);
+    if (status != REPORT_TOOL_STATUS_NULL_ARGUMENT || err == NULL) {
+        fprintf(stderr, "expected null argument, got status %d\n", (int)status);
+        return 1;
+    }
+    report_tool_string_free(err);
+
+    printf("ok\n");
+    return 0;
+}
@@ -0,0 +1,85 @@
+use crate::ffi::*;
+use std::ffi::{CStr, CString};
+use std::path::PathBuf;
+use std::process::Command;
+use std::ptr;
+
+const DATA_PATH: &'static str = "./src/tests/";
+
+#[test]
+fn ffi_dataset_from_buffers() {
+    let meta = std::fs::read(format!("{}{}", DATA_PATH, "meta_test.json")).unwrap();
+    let input = std::fs::read(format!("{}{}", DATA_PATH, "input_test.csv")).unwrap();
+    let lang = CString::new("ja").unwrap();
+    let mut ds = ptr::null_mut();
+    let mut err = ptr::null_mut();
+    unsafe {
+        let status = report_tool_dataset_from_buffers(meta.as_ptr(), meta.len(), input.as_ptr(),
+                                                      input.len(), lang.as_ptr(), 2020,
+                                                      ptr::null(), ptr::null(), 0, &mut ds,
+                                                      &mut err);
+        assert_eq!(status, ReportToolStatus::Ok);
+        assert!(err.is_null());
+        assert!(report_tool_dataset_len(ds) > 0);
+        report_tool_dataset_free(ds);
+
+        let mut ds = ptr::null_mut();
+        let status = report_tool_dataset_from_buffers(b"{".as_ptr(), 1, input.as_ptr(),
+                                                      input.len(), lang.as_ptr(), 2020,
+                                                      ptr::null(), ptr::null(), 0, &mut ds,
+                                                      &mut err);
+        assert_eq!(status, ReportToolStatus::MetadataWrongFormat);
+        assert!(ds.is_null());
+        assert!(!CStr::from_ptr(err).to_str().unwrap().is_empty());
+        report_tool_string_free(err);
+    }
+}
+
+/// Compile `ffi_smoke.c` against the generated header and the cdylib, then run it.
+///
+/// Skipped with the `python` feature, where the cdylib also needs libpython at link time.
+#[test]
+#[cfg(not(feature = "python"))]
+fn ffi_c_smoke() {
+    // <target>/debug/deps/report_tool-<hash> -> <target>/debug
+    let exe = std::env::current_exe().unwrap();
+    let target_dir = exe.parent().and_then(|p| p.parent()).unwrap().to_path_buf();
+    let lib = ["libreport_tool.so", "libreport_tool.dylib"]
+        .iter()
+        .map(|name| target_dir.join(name))
+        .find(|path| path.is_file());
+    let lib = match lib {
+        Some(lib) => lib,
+        None => {
+            eprintln!("skipping ffi_c_smoke: cdylib not found in {:?}", target_dir);
+            return;
+        }
+    };
+    let smoke = target_dir.join("ffi_smoke");
+    let compiled = Command::new("cc")
+        .arg(format!("{}{}", DATA_PATH, "ffi_smoke.c"))
+        .arg("-Iinclude")
+        .arg("-o")
+        .arg(&smoke)
+        .arg(&lib)
+        .arg(format!("-Wl,-rpath,{}", target_dir.display()))
+        .status();
+    match compiled {
+        Ok(status) => assert!(status.success(), "ffi_smoke.c failed to compile"),
+        Err(_) => {
+            eprintln!("skipping ffi_c_smoke: no C compiler");
+            return;
+        }
+    }
+
+    let output: PathBuf = target_dir.join("ffi_smoke.xlsx");
+    let res = Command::new(&smoke)
+        .arg(format!("{}{}", DATA_PATH, "meta_test.json"))
+        .arg(format!("{}{}", DATA_PATH, "input_test.csv"))
+        .arg(&output)
+        .output()
+        .unwrap();
+    assert!(res.status.success(), "{}", String::from_utf8_lossy(&res.stderr));
+    assert!(output.is_file());
+    std::fs::remove_file(output).unwrap();
+}
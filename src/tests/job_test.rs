@@ -0,0 +1,134 @@
+use crate::errors::RustlyzerError;
+use crate::job::{batch, ReportJob};
+use crate::writer::Sheet;
+use std::path::Path;
+
+#[test]
+fn job_from_toml() {
+    let job = ReportJob::from_toml(r#"
+        meta = "meta_test.json"
+        input = "input_test.csv"
+        output = "out.xlsx"
+        created_year = 2020
+        sheets = ["aggregate", "crosstab_perc"]
+
+        [includes]
+        gender = ["男性", "女性"]
+    "#).unwrap();
+    assert_eq!(job.lang, "ja");
+    assert_eq!(job.sheets, vec![Sheet::Aggregate, Sheet::CrosstabPerc]);
+    assert_eq!(job.include_tuples().len(), 2);
+    assert_eq!(job.config().unwrap().includes.len(), 2);
+}
+
+#[test]
+fn job_from_json_defaults_to_all_sheets() {
+    let job = ReportJob::from_json(r#"{
+        "meta": "meta_test.json",
+        "input": "input_test.csv",
+        "output": "out.xlsx",
+        "lang": "en",
+        "created_year": 2020
+    }"#).unwrap();
+    assert_eq!(job.sheets, Sheet::get_all());
+    assert!(job.includes.is_empty());
+}
+
+#[test]
+fn job_unknown_field_rejected() {
+    let res = ReportJob::from_json(r#"{
+        "meta": "meta_test.json",
+        "input": "input_test.csv",
+        "output": "out.xlsx",
+        "created_year": 2020,
+        "year": 2021
+    }"#);
+    assert!(matches!(res, Err(RustlyzerError::JobWrongFormat(_))));
+}
+
+#[test]
+fn job_validate() {
+    let mut job = ReportJob::new("meta_test.json", "input_test.csv", "out.xlsx", "ja", 2020,
+                                 vec![("gender".to_string(), "男性".to_string())]);
+    job.resolve_paths(Path::new("./src/tests/"));
+    assert!(job.validate().is_ok());
+
+    job.lang = "fr".to_string();
+    assert!(matches!(job.validate(), Err(RustlyzerError::InvalidConfigValError { .. })));
+    job.lang = "ja".to_string();
+
+    job.includes.insert("gender".to_string(), vec!["unknown".to_string()]);
+    assert!(job.validate().is_err());
+    job.includes.clear();
+
+    job.sheets = vec![Sheet::Aggregate, Sheet::Aggregate];
+    assert!(matches!(job.validate(), Err(RustlyzerError::JobWrongFormat(_))));
+}
+
+#[test]
+fn sheet_from_id_or_name() {
+    assert_eq!("crosstab_perc".parse::<Sheet>().unwrap(), Sheet::CrosstabPerc);
+    assert_eq!("crosstab(%)".parse::<Sheet>().unwrap(), Sheet::CrosstabPerc);
+    assert_eq!("index".parse::<Sheet>().unwrap(), Sheet::Index);
+    assert!(!Sheet::get_all().contains(&Sheet::Index));
+    assert!(matches!("summary".parse::<Sheet>(),
+                     Err(RustlyzerError::InvalidConfigValError { .. })));
+}
+
+#[test]
+fn job_run_rejects_repeated_sheets() {
+    let mut job = ReportJob::new("./src/tests/meta_test.json", "./src/tests/input_test.csv",
+                                 "./out.xlsx", "ja", 2020, vec![]);
+    job.sheets = vec![Sheet::CrosstabN, Sheet::UserGraph, Sheet::CrosstabN];
+    assert!(matches!(job.run(), Err(RustlyzerError::InvalidConfigItemError(_))));
+    job.sheets = vec![Sheet::UserGraph, Sheet::Index];
+    assert!(matches!(job.run(), Err(RustlyzerError::InvalidConfigItemError(_))));
+    assert!(!Path::new("./out.xlsx").exists());
+}
+
+#[test]
+fn batch_pairs_from_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["a.json", "a.csv", "b.json", "c.csv", "notes.txt"].iter() {
+        std::fs::write(dir.path().join(name), b"").unwrap();
+    }
+    std::fs::create_dir(dir.path().join("d.json")).unwrap();
+    let (jobs, unmatched) = batch::jobs_from_dir(dir.path(), Path::new("./out/"), "ja", 2020,
+                                                 vec![]).unwrap();
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0].meta, dir.path().join("a.json"));
+    assert_eq!(jobs[0].input, dir.path().join("a.csv"));
+    assert_eq!(jobs[0].output, Path::new("./out/a.xlsx"));
+    assert_eq!(unmatched, vec![dir.path().join("b.json"), dir.path().join("c.csv")]);
+}
+
+#[test]
+fn batch_failure_does_not_abort() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("ok.xlsx");
+    let mut valid = ReportJob::new("./src/tests/meta_test.json", "./src/tests/input_test.csv",
+                                   &output.to_string_lossy(), "ja", 2020, vec![]);
+    valid.sheets = vec![Sheet::Aggregate];
+    let jobs = vec![
+        ReportJob::new("missing.json", "missing.csv", "missing.xlsx", "ja", 2020, vec![]),
+        valid,
+        ReportJob::new("./src/tests/meta_test.json", "./src/tests/input_test.csv",
+                       "./out.xlsx", "ja", 2020,
+                       vec![("gender".to_string(), "unknown".to_string())]),
+    ];
+    let summary = batch::run_batch(jobs, Some(2)).unwrap();
+    assert_eq!(summary.outcomes.len(), 3);
+    assert_eq!(summary.failed().len(), 2);
+    assert!(!summary.is_success());
+    // Outcomes keep the order of the jobs
+    let run = summary.outcomes[1].result.as_ref().unwrap();
+    assert_eq!((run.kind, run.rejected.as_ref()), ("Xlsx", None));
+    assert_eq!(summary.outcomes[1].job.output, output);
+    assert!(output.exists());
+    // Each job is reported on its own line of the summary
+    let text = summary.to_string();
+    assert!(text.starts_with("1 succeeded, 2 failed\n"), "{}", text);
+    let ok_line = format!("  ok     {:?} in {} ms\n", output, run.total_time);
+    assert!(text.contains(&ok_line), "{}", text);
+    assert!(text.contains("  failed \"missing.csv\": "), "{}", text);
+}
@@ -5,8 +5,13 @@ use crate::ds::{field::FieldType, DataSet, DataSetConfig, Language};
 use std::fs::File;
 use std::io::*;
 mod ds_test;
+mod ffi_test;
+mod job_test;
+mod python_test;
+mod server_test;
 mod static_fields_test;
 mod table_test;
+mod writer_test;
 // Helpers
 fn test_static_field_str(ind: usize, field: FieldType, expected: &'static str) {
     let ds = get_test_ds();
@@ -0,0 +1,107 @@
+#![cfg(feature = "python")]
+use super::get_test_ds;
+use crate::ds::table::{CellValue, SpecialCase, Table};
+use crate::ds::CrosstabType;
+use crate::errors::RustlyzerError;
+use crate::python::*;
+use pyo3::prelude::*;
+
+#[test]
+fn python_error_maps_to_exception_class() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let err: PyErr = RustlyzerError::CsvInputWrongFormat("bad".to_string()).into();
+        assert!(err.is_instance_of::<CsvInputWrongFormatError>(py));
+        assert!(err.is_instance_of::<RustlyzerException>(py));
+        let err: PyErr = RustlyzerError::IndexOutOfRange.into();
+        assert!(err.is_instance_of::<RustlyzerException>(py));
+        assert!(!err.is_instance_of::<InternalError>(py));
+    });
+}
+
+/// The test files loaded through the python classes.
+fn py_dataset(py: Python) -> &PyAny {
+    let config = py.get_type::<PyDataSetConfig>().call1(("ja", 2020)).unwrap();
+    py.get_type::<PyDataSet>()
+        .call_method1("from_files", ("./src/tests/meta_test.json", config,
+                                     "./src/tests/input_test.csv"))
+        .unwrap()
+}
+
+fn assert_py_cell(cell: &PyAny, value: &CellValue) {
+    match value {
+        CellValue::Text(text) => assert_eq!(&cell.extract::<String>().unwrap(), text),
+        CellValue::Integer(n) => assert_eq!(cell.extract::<i64>().unwrap(), *n),
+        CellValue::Float(n) | CellValue::Percentage(n) => {
+            let got = cell.extract::<f64>().unwrap();
+            assert!(got == *n || (got.is_nan() && n.is_nan()), "{} != {}", got, n);
+        },
+        CellValue::Date(date) => assert_eq!(cell.extract::<String>().unwrap(),
+                                            date.format("%Y-%m-%dT%H:%M:%S").to_string()),
+        CellValue::Empty => assert!(cell.is_none()),
+    }
+}
+
+/// Check the dict of a table against `table`, cell by cell.
+fn assert_py_table(dict: &PyAny, table: &Table) {
+    let headers = dict.get_item("headers").unwrap().extract::<Vec<String>>().unwrap();
+    assert_eq!(headers, table.cols.iter().map(|col| col.header.text.clone()).collect::<Vec<_>>());
+    let highlights = dict.get_item("highlights").unwrap().extract::<Vec<bool>>().unwrap();
+    assert_eq!(highlights, table.cols.iter().map(|col| col.header.highlight).collect::<Vec<_>>());
+    let (rows, footers) = (dict.get_item("rows").unwrap(), dict.get_item("footers").unwrap());
+    let row_count = table.cols.iter().map(|col| col.contents.len()).max().unwrap_or(0);
+    assert_eq!(rows.len().unwrap(), row_count);
+    assert_eq!(footers.len().unwrap(), table.cols.len());
+    for (col_index, col) in table.cols.iter().enumerate() {
+        for row in 0..row_count {
+            let cell = rows.get_item(row).unwrap().get_item(col_index).unwrap();
+            assert_py_cell(cell, col.contents.get(row).unwrap_or(&CellValue::Empty));
+        }
+        assert_py_cell(footers.get_item(col_index).unwrap(),
+                       col.footer.as_ref().unwrap_or(&CellValue::Empty));
+    }
+}
+
+#[test]
+fn python_dataset_tables_as_dicts() {
+    pyo3::prepare_freethreaded_python();
+    let ds = get_test_ds();
+    Python::with_gil(|py| {
+        let config = py.get_type::<PyDataSetConfig>().call1(("en", 2021)).unwrap();
+        assert_eq!(config.getattr("language").unwrap().extract::<String>().unwrap(), "en");
+        assert_eq!(config.getattr("created_year").unwrap().extract::<u16>().unwrap(), 2021);
+
+        let dataset = py_dataset(py);
+        assert_eq!(dataset.len().unwrap(), ds.data.len());
+        let raw = dataset.call_method0("get_fkc_raw_table").unwrap();
+        assert_eq!(raw.get_item("rows").unwrap().len().unwrap(), 300);
+        assert_py_table(raw, &ds.get_fkc_raw_table().unwrap());
+        let job = dataset.call_method0("get_user_graph_table_job").unwrap();
+        assert_py_table(job, &ds.get_user_graph_table_job().unwrap());
+
+        for (arg, crosstab_type) in [("n", CrosstabType::N), ("perc", CrosstabType::Perc)].iter() {
+            let tables = ds.get_crosstab_tables(*crosstab_type).unwrap();
+            let list = dataset.call_method1("get_crosstab_tables", (*arg,)).unwrap();
+            assert_eq!(list.len().unwrap(), tables.len());
+            for (i, table) in tables.iter().enumerate() {
+                let dict = list.get_item(i).unwrap();
+                assert_eq!(dict.get_item("meta").unwrap().extract::<Vec<String>>().unwrap(),
+                           table.meta);
+                let special_case = match table.special_case {
+                    SpecialCase::None => "none",
+                    SpecialCase::SpaceAndHighlightOn4 => "space_and_highlight_on4",
+                };
+                assert_eq!(dict.get_item("special_case").unwrap().extract::<String>().unwrap(),
+                           special_case);
+                assert_py_table(dict.get_item("table").unwrap(), &table.table);
+            }
+        }
+        // Counts by default
+        let default = dataset.call_method0("get_crosstab_tables").unwrap();
+        assert_eq!(default.len().unwrap(), ds.get_crosstab_tables(CrosstabType::N).unwrap().len());
+
+        let err = dataset.call_method1("get_crosstab_tables", ("pct",)).unwrap_err();
+        assert!(err.is_instance_of::<InvalidConfigError>(py));
+        assert!(err.value(py).to_string().contains("crosstab_type"), "{}", err.value(py));
+    });
+}
@@ -0,0 +1,141 @@
+use crate::server::{ErrorBody, ReportServer};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+
+const DATA_PATH: &'static str = "./src/tests/";
+const BOUNDARY: &'static str = "report-tool-test-boundary";
+
+fn start_server() -> SocketAddr {
+    let server = ReportServer::bind("127.0.0.1:0").unwrap();
+    let addr = server.addr();
+    std::thread::spawn(move || server.run());
+    addr
+}
+
+/// Send a raw request and return the status code and body.
+fn send(addr: SocketAddr, head: &str, body: &[u8]) -> (u16, Vec<u8>) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(stream, "{}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", head, body.len())
+        .unwrap();
+    stream.write_all(body).unwrap();
+    let mut response = Vec::<u8>::new();
+    stream.read_to_end(&mut response).unwrap();
+    let split = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+    let status_line = String::from_utf8_lossy(&response[..split]).to_string();
+    let code = status_line.split(' ').nth(1).unwrap().parse::<u16>().unwrap();
+    (code, response[split + 4..].to_vec())
+}
+
+fn multipart_body(parts: &[(&str, String)]) -> Vec<u8> {
+    let mut body = String::new();
+    for (name, content) in parts.iter() {
+        body.push_str(&format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                               BOUNDARY, name, content));
+    }
+    body.push_str(&format!("--{}--\r\n", BOUNDARY));
+    body.into_bytes()
+}
+
+fn post_report(addr: SocketAddr, parts: &[(&str, String)]) -> (u16, Vec<u8>) {
+    let head = format!("POST /report HTTP/1.1\r\nHost: localhost\r\n\
+                        Content-Type: multipart/form-data; boundary={}", BOUNDARY);
+    send(addr, &head, &multipart_body(parts))
+}
+
+fn read_test_file(name: &str) -> String {
+    std::fs::read_to_string(format!("{}{}", DATA_PATH, name)).unwrap()
+}
+
+#[test]
+fn server_include_criteria() {
+    let addr = start_server();
+    let (code, body) = send(addr, "GET /include-criteria HTTP/1.1\r\nHost: localhost", b"");
+    assert_eq!(code, 200);
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(json.as_array().unwrap().iter().any(|item| item[0] == "gender"));
+
+    let (code, body) = send(addr, "GET /unknown HTTP/1.1\r\nHost: localhost", b"");
+    assert_eq!(code, 404);
+    assert_eq!(serde_json::from_slice::<ErrorBody>(&body).unwrap().kind, "RequestWrongFormat");
+}
+
+#[test]
+fn server_report() {
+    let addr = start_server();
+    let config = r#"{"lang": "ja", "created_year": 2020, "sheets": ["aggregate"]}"#.to_string();
+    let (code, body) = post_report(addr, &[
+        ("meta", read_test_file("meta_test.json")),
+        ("csv", read_test_file("input_test.csv")),
+        ("config", config.to_owned()),
+    ]);
+    assert_eq!(code, 200);
+    assert!(!body.is_empty());
+
+    let (code, body) = post_report(addr, &[
+        ("meta", "{".to_string()),
+        ("csv", read_test_file("input_test.csv")),
+        ("config", config.to_owned()),
+    ]);
+    assert_eq!(code, 400);
+    assert_eq!(serde_json::from_slice::<ErrorBody>(&body).unwrap().kind, "MetadataWrongFormat");
+
+    let (code, body) = post_report(addr, &[("meta", read_test_file("meta_test.json"))]);
+    assert_eq!(code, 400);
+    let error = serde_json::from_slice::<ErrorBody>(&body).unwrap();
+    assert_eq!(error.kind, "RequestWrongFormat");
+    assert!(error.message.contains("csv"));
+
+    let config = r#"{"created_year": 2020, "includes": {"gender": ["unknown"]}}"#.to_string();
+    let (code, body) = post_report(addr, &[
+        ("meta", read_test_file("meta_test.json")),
+        ("csv", read_test_file("input_test.csv")),
+        ("config", config),
+    ]);
+    assert_eq!(code, 400);
+    let error = serde_json::from_slice::<ErrorBody>(&body).unwrap();
+    assert_eq!(error.kind, "InvalidConfigValError");
+    assert_eq!(error.detail["val"], "unknown");
+}
+
+#[test]
+fn server_request_limits() {
+    let addr = start_server();
+    // Routes match the path whatever the query
+    let (code, _) = send(addr, "GET /include-criteria?lang=ja HTTP/1.1\r\nHost: localhost", b"");
+    assert_eq!(code, 200);
+
+    let head = format!("POST /report HTTP/1.1\r\nHost: localhost\r\n\
+                        Content-Type: multipart/form-data; boundary={}", BOUNDARY);
+    let body = format!("--{}\r\nContent-Disposition: form-data; name=\"meta\"\r\n\r\n{{", BOUNDARY);
+    let (code, body) = send(addr, &head, body.as_bytes());
+    assert_eq!(code, 400);
+    let error = serde_json::from_slice::<ErrorBody>(&body).unwrap();
+    assert_eq!(error.kind, "RequestWrongFormat");
+    assert!(error.message.contains("multipart"), "{}", error.message);
+
+    let server = ReportServer::bind("127.0.0.1:0").unwrap().with_max_body_len(1024);
+    let small = server.addr();
+    std::thread::spawn(move || server.run());
+    let (code, body) = post_report(small, &[
+        ("meta", read_test_file("meta_test.json")),
+        ("csv", read_test_file("input_test.csv")),
+        ("config", r#"{"created_year": 2020}"#.to_string()),
+    ]);
+    assert_eq!(code, 413);
+    assert_eq!(serde_json::from_slice::<ErrorBody>(&body).unwrap().kind, "RequestTooLarge");
+}
+
+#[test]
+fn server_workers_share_requests() {
+    // No worker at all is taken as one
+    let server = ReportServer::bind("127.0.0.1:0").unwrap().with_workers(0);
+    let addr = server.addr();
+    std::thread::spawn(move || server.run());
+    let clients = (0..4)
+        .map(|_| std::thread::spawn(move || send(
+            addr, "GET /include-criteria HTTP/1.1\r\nHost: localhost", b"").0))
+        .collect::<Vec<std::thread::JoinHandle<u16>>>();
+    for client in clients {
+        assert_eq!(client.join().unwrap(), 200);
+    }
+}
@@ -1,4 +1,6 @@
 use super::*;
+use crate::ds::table::{CellRef, CellValue, Formula, Total};
+use crate::ds::CrosstabType;
 
 #[test]
 fn fkc_column_count_correct() {
@@ -18,7 +20,7 @@ fn fkc_row_count_correct() {
 fn fkc_multiselect_str_correct() {
     let correct_str = r#"["【個人】小学校等の臨時休業に対応する保護者支援", "【個人・法人】企業主導型ベビーシッター利用者支援事業（特例措置）", "【個人・個人事業主】国民健康保険料（税）の減免に対する財政支援", "【個人】特別定額給付金", "【個人事業主・法人】小規模事業者持続化補助金（特別枠）"]"#;
     let fkc_table = get_test_ds().get_fkc_raw_table().unwrap();
-    assert_eq!(fkc_table.cols[16].contents[12].as_str(), correct_str);
+    assert_eq!(fkc_table.cols[16].contents[12], CellValue::from(correct_str));
 }
 
 #[test]
@@ -26,10 +28,92 @@ fn fkc_multiselect_correct() {
     let correct_data: Vec<u8> = vec![1, 1, 1, 1, 0, 0, 0, 0, 0, 1, 0, 0];
     let correct_data = correct_data
         .into_iter()
-        .map(|num| num.to_string())
-        .collect::<Vec<String>>();
+        .map(|num| CellValue::Integer(num as i64))
+        .collect::<Vec<CellValue>>();
     let fkc_table = get_test_ds().get_fkc_raw_table().unwrap();
     for i in 17..=28 {
         assert_eq!(fkc_table.cols[i].contents[12], correct_data[i - 17]);
     }
 }
+
+#[test]
+fn table_values_are_typed() {
+    let ds = get_test_ds();
+    let it_table = ds.get_it_raw_table().unwrap();
+    // id, user_id, created_at, gender, prefecture, region, age
+    assert!(matches!(it_table.cols[0].contents[0], CellValue::Text(_)));
+    assert!(matches!(it_table.cols[2].contents[0], CellValue::Date(_)));
+    assert!(matches!(it_table.cols[6].contents[0], CellValue::Integer(_)));
+
+    let aggregate = &ds.get_aggregate_tables().unwrap()[0].table;
+    assert!(matches!(aggregate.cols[0].contents[0], CellValue::Text(_)));
+    assert!(matches!(aggregate.cols[1].contents[0], CellValue::Integer(_)));
+    assert!(matches!(aggregate.cols[2].contents[0], CellValue::Percentage(_)));
+
+    let job = ds.get_user_graph_table_job().unwrap();
+    assert_eq!(job.cols[1].footer, Some(CellValue::Integer(ds.data.len() as i64)));
+    assert_eq!(job.cols[3].footer, Some(CellValue::Percentage(100.0)));
+}
+
+#[test]
+fn formulas_match_cached_values() {
+    let ds = get_test_ds();
+    let job = ds.get_user_graph_table_job().unwrap();
+    assert_eq!(job.cols[1].footer_formula, Some(Formula::ColumnSum));
+    assert_eq!(job.cols[3].formula(0), Some(&Formula::Share {
+        count: CellRef::Table { col: 1, row: 0 },
+        total: Total::Footer(1),
+    }));
+
+    let counts = ds.get_crosstab_tables(CrosstabType::N).unwrap();
+    let percs = ds.get_crosstab_tables(CrosstabType::Perc).unwrap();
+    let count_at = |table: usize, col: usize, row: usize| {
+        counts[table].table.cols[col].contents[row].as_f64().unwrap()
+    };
+    let mut checked = 0;
+    for (table_index, perc) in percs.iter().enumerate() {
+        for col in perc.table.cols.iter() {
+            for (row, value) in col.contents.iter().enumerate() {
+                if let Some(Formula::Share {
+                    count: CellRef::Counts { col: count_col, row: count_row },
+                    total: Total::RowSum { first: CellRef::Counts { col: first_col, .. }, last_col },
+                }) = col.formula(row) {
+                    assert_eq!(*count_row, row);
+                    let total = (*first_col..=*last_col)
+                        .map(|c| count_at(table_index, c, row))
+                        .sum::<f64>();
+                    if total == 0.0 {
+                        // Empty rows have no percentage to recompute
+                        assert!(value.as_f64().unwrap().is_nan());
+                        continue;
+                    }
+                    let expected = count_at(table_index, *count_col, row) * 100.0 / total;
+                    assert!((value.as_f64().unwrap() - expected).abs() < 1e-9);
+                    checked += 1;
+                }
+            }
+        }
+    }
+    assert!(checked > 0);
+}
+
+#[test]
+fn json_report_sections() {
+    use crate::writer::{JsonReport, Sheet};
+    let ds = get_test_ds();
+    let report = JsonReport::from_dataset(&ds, &[Sheet::UserGraph, Sheet::Aggregate]).unwrap();
+    assert_eq!(report.sections.len(), 2);
+    assert_eq!(report.sections[0].tables.len(), 8);
+    assert_eq!(report.sections[1].tables.len(), ds.get_aggregate_tables().unwrap().len());
+
+    let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+    let section = &json["sections"][1];
+    assert_eq!(section["sheet"], "aggregate");
+    assert_eq!(section["name"], Sheet::Aggregate.sheet_name());
+    let table = &section["tables"][0];
+    assert_eq!(table["meta"].as_array().unwrap().len(), 3);
+    assert_eq!(table["special_case"], "none");
+    assert_eq!(table["table"]["cols"][0]["header"]["text"], "選択肢");
+    assert!(table["table"]["cols"][0]["contents"].as_array().unwrap().len() > 0);
+    assert!(table["table"]["cols"][0]["footer"].is_null());
+}
@@ -0,0 +1,317 @@
+use super::get_test_ds;
+use crate::ds::table::{CellValue, Column, Header, SpecialCase, Table, TableWithMeta};
+use crate::errors::RustlyzerError;
+use crate::writer::*;
+
+/// Records what `write_report` asks of a backend.
+#[derive(Default)]
+struct RecordingWriter {
+    sheets: Vec<Sheet>,
+    layout: SheetLayout,
+    tables: Vec<(Sheet, TableWithMeta, TableRanges)>,
+    charts: Vec<(Sheet, ChartSpec)>,
+    index: Vec<IndexEntry>,
+    finished: bool,
+}
+
+impl ReportWriter for RecordingWriter {
+    fn begin_sheet(&mut self, sheet: Sheet) -> Result<(), RustlyzerError> {
+        self.sheets.push(sheet);
+        self.layout = SheetLayout::new();
+        Ok(())
+    }
+
+    fn write_table(&mut self, table: &TableWithMeta, placement: Placement)
+        -> Result<TableRanges, RustlyzerError> {
+        let ranges = self.layout.place(table, placement, |_, _| Ok(()))?;
+        self.tables.push((*self.sheets.last().unwrap(), table.clone(), ranges.clone()));
+        Ok(ranges)
+    }
+
+    fn write_heading(&mut self, _heading: &Heading) -> Result<(), RustlyzerError> {
+        Ok(())
+    }
+
+    fn write_chart(&mut self, chart: &ChartSpec) -> Result<(), RustlyzerError> {
+        self.charts.push((*self.sheets.last().unwrap(), chart.clone()));
+        Ok(())
+    }
+
+    fn write_index(&mut self, entries: &[IndexEntry]) -> Result<(), RustlyzerError> {
+        self.index = entries.to_vec();
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), RustlyzerError> {
+        self.finished = true;
+        Ok(())
+    }
+}
+
+fn small_table(meta: Vec<String>, rows: usize) -> TableWithMeta {
+    let col = |text: &str| Column::from_contents(Header::new(text.to_string(), false),
+                                                 vec![CellValue::Integer(1); rows],
+                                                 Some("計".into()));
+    TableWithMeta::new(meta, Table::new(vec![col("a"), col("b")]))
+}
+
+#[test]
+fn report_writer_receives_selected_sheets() {
+    let ds = get_test_ds();
+    let mut writer = RecordingWriter::default();
+    let sheets = [Sheet::Aggregate, Sheet::UserGraph];
+    write_report(&mut writer, &ds, &sheets).unwrap();
+    assert!(writer.finished);
+    assert_eq!(writer.sheets, sheets.to_vec());
+    assert_eq!(writer.tables.iter().filter(|(s, _, _)| *s == Sheet::UserGraph).count(), 8);
+    assert_eq!(writer.tables.iter().filter(|(s, _, _)| *s == Sheet::Aggregate).count(),
+               ds.get_aggregate_tables().unwrap().len());
+    assert!(writer.charts.iter().all(|(s, _)| *s == Sheet::UserGraph));
+}
+
+#[test]
+fn index_links_to_written_tables() {
+    let ds = get_test_ds();
+    let mut writer = RecordingWriter::default();
+    write_report(&mut writer, &ds, &[Sheet::Index, Sheet::Aggregate, Sheet::CrosstabN]).unwrap();
+    assert!(writer.tables.iter().all(|(s, _, _)| *s != Sheet::Index));
+
+    let sheet_entries = writer.index.iter()
+        .filter(|entry| entry.title == entry.sheet.sheet_name())
+        .map(|entry| (entry.sheet, entry.start))
+        .collect::<Vec<(Sheet, CellCoord)>>();
+    assert_eq!(sheet_entries, vec![(Sheet::Aggregate, CellCoord::new(0, 0)),
+                                   (Sheet::CrosstabN, CellCoord::new(0, 0))]);
+    let aggregate = writer.tables.iter().filter(|(s, _, _)| *s == Sheet::Aggregate)
+        .map(|(_, table, ranges)| (table.meta[0].to_owned(), ranges.start))
+        .collect::<Vec<(String, CellCoord)>>();
+    let aggregate_entries = writer.index.iter()
+        .filter(|entry| entry.sheet == Sheet::Aggregate && entry.title != "aggregate")
+        .map(|entry| (entry.title.to_owned(), entry.start))
+        .collect::<Vec<(String, CellCoord)>>();
+    assert_eq!(aggregate_entries, aggregate);
+    let crosstab_bases = writer.tables.iter()
+        .filter(|(s, table, _)| *s == Sheet::CrosstabN
+            && matches!(table.special_case, SpecialCase::SpaceAndHighlightOn4))
+        .map(|(_, table, ranges)| (table.table.cols[2].header.text.to_owned(), ranges.start))
+        .collect::<Vec<(String, CellCoord)>>();
+    let crosstab_entries = writer.index.iter()
+        .filter(|entry| entry.sheet == Sheet::CrosstabN && entry.title != "crosstab(n)")
+        .map(|entry| (entry.label.to_owned(), entry.start))
+        .collect::<Vec<(String, CellCoord)>>();
+    assert_eq!(crosstab_entries, crosstab_bases);
+    assert_eq!(crosstab_entries[1].0, "性別");
+
+    // Every link of the html index points at an element of the page
+    let mut html = HtmlReportWriter::new("test");
+    write_report(&mut html, &ds, &[Sheet::Index, Sheet::Aggregate]).unwrap();
+    let page = html.html();
+    let links = page.split("<a href=\"#").skip(1)
+        .map(|rest| rest.split('"').next().unwrap())
+        .collect::<Vec<&str>>();
+    assert_eq!(links.len(), aggregate.len() + 1);
+    for link in links.iter() {
+        assert!(page.contains(&format!("id=\"{}\"", link)), "{}", link);
+    }
+}
+
+#[test]
+fn sheet_layout_placements() {
+    let mut layout = SheetLayout::new();
+    let mut cells = Vec::<CellCoord>::new();
+    let ranges = layout.place(&small_table(vec!["title".to_string()], 3),
+                              Placement::At(CellCoord::new(0, 1)),
+                              |coord, _| { cells.push(coord); Ok(()) }).unwrap();
+    assert_eq!(ranges.start, CellCoord::new(0, 1));
+    assert_eq!(ranges.columns, vec![CellRange::new(2, 1, 4, 1), CellRange::new(2, 2, 4, 2)]);
+    // meta, 2 headers, 6 contents, 2 footers
+    assert_eq!(cells.len(), 11);
+    assert_eq!(cells.last(), Some(&CellCoord::new(5, 2)));
+
+    let ranges = layout.place(&small_table(vec![], 1), Placement::Below(2), |_, _| Ok(())).unwrap();
+    assert_eq!(ranges.start, CellCoord::new(7, 1));
+    let ranges = layout.place(&small_table(vec![], 1), Placement::Right(2), |_, _| Ok(())).unwrap();
+    assert_eq!(ranges.start, CellCoord::new(0, 5));
+}
+
+#[test]
+fn user_graph_charts_reference_their_tables() {
+    let ds = get_test_ds();
+    let mut writer = RecordingWriter::default();
+    write_report(&mut writer, &ds, &[Sheet::UserGraph]).unwrap();
+    let expected = |title: &str| -> Vec<Table> {
+        match title {
+            "年代" => vec![ds.get_user_graph_table_age1060().unwrap(),
+                         ds.get_user_graph_table_age1070().unwrap()],
+            "性別" => vec![ds.get_user_graph_table_gender().unwrap()],
+            "未既婚" => vec![ds.get_user_graph_table_marital().unwrap()],
+            "子供の人数" => vec![ds.get_user_graph_table_children().unwrap()],
+            "職業" => vec![ds.get_user_graph_table_job().unwrap()],
+            "地域" => vec![ds.get_user_graph_table_region().unwrap()],
+            "世帯年収" => vec![ds.get_user_graph_table_income().unwrap()],
+            title => panic!("unexpected chart {}", title),
+        }
+    };
+    assert_eq!(writer.charts.len(), 16);
+    for (_, chart) in writer.charts.iter() {
+        let (_, table, ranges) = writer.tables.iter()
+            .find(|(_, _, ranges)| ranges.columns.contains(&chart.categories))
+            .unwrap_or_else(|| panic!("categories of {} are not a table column", chart.title));
+        assert!(ranges.columns.contains(&chart.values),
+                "values of {} are not in the same table", chart.title);
+        let table = serde_json::to_value(&table.table).unwrap();
+        assert!(expected(&chart.title).iter().any(|t| serde_json::to_value(t).unwrap() == table),
+                "{} is drawn from another table", chart.title);
+    }
+}
+
+#[test]
+fn every_chart_takes_a_palette_entry() {
+    let palette = ["#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2",
+        "#7f7f7f"];
+    let theme = Theme::from_json(&format!(r#"{{ "chart_palette": {:?} }}"#, palette)).unwrap();
+    let mut writer = RecordingWriter::default();
+    write_report(&mut writer, &get_test_ds(), &[Sheet::UserGraph]).unwrap();
+    let fills = writer.charts.iter()
+        .map(|(_, chart)| theme.chart_color(chart.fill))
+        .collect::<Vec<Color>>();
+    // The charts with and without n-number are filled alike, each with its own colour
+    let (with_n, without_n) = fills.split_at(fills.len() / 2);
+    assert_eq!(with_n, without_n);
+    assert_eq!(with_n.to_vec(), theme.chart_palette);
+
+    let mut html = HtmlReportWriter::with_theme("test", &theme);
+    write_report(&mut html, &get_test_ds(), &[Sheet::UserGraph]).unwrap();
+    for color in palette.iter() {
+        assert!(html.html().contains(&format!("fill=\"{}\"", color)), "{}", color);
+    }
+}
+
+/// Formula cells of the tables of `sheet`, `sheets` being written in order.
+fn formulas_of(ds: &crate::ds::DataSet, sheets: &[Sheet], sheet: Sheet) -> Vec<FormulaCell> {
+    let mut writer = RecordingWriter::default();
+    write_report(&mut writer, ds, sheets).unwrap();
+    let begun = &writer.sheets[..=writer.sheets.iter().position(|s| *s == sheet).unwrap()];
+    writer.tables.iter()
+        .filter(|(s, _, _)| *s == sheet)
+        .flat_map(|(_, table, ranges)| formula_cells(table, ranges, begun).unwrap())
+        .collect()
+}
+
+fn formula_at<'c>(cells: &'c [FormulaCell], a1: &str) -> Option<&'c str> {
+    cells.iter().find(|cell| cell.coord.a1() == a1).map(|cell| cell.formula.as_str())
+}
+
+#[test]
+fn formulas_written_as_a1_references() {
+    let ds = get_test_ds();
+    // Job table of the user graph sheet: counts in F2:F13, total in F14, shares in H
+    let cells = formulas_of(&ds, &[Sheet::UserGraph], Sheet::UserGraph);
+    assert_eq!(formula_at(&cells, "F14"), Some("=SUM(F2:F13)"));
+    assert_eq!(formula_at(&cells, "H2"), Some("=F2*100/F14"));
+    let share = cells.iter().find(|cell| cell.coord.a1() == "H2").unwrap();
+    assert!(matches!(share.value, CellValue::Percentage(n) if n > 0.0));
+
+    let cross_sheet = "='crosstab(n)'!E7*100/SUM('crosstab(n)'!E7:J7)";
+    let cells = formulas_of(&ds, &[Sheet::CrosstabN, Sheet::CrosstabPerc], Sheet::CrosstabPerc);
+    assert_eq!(formula_at(&cells, "E7"), Some(cross_sheet));
+    // Without the counts written before, the cells keep their cached values
+    for sheets in [vec![Sheet::CrosstabPerc], vec![Sheet::CrosstabPerc, Sheet::CrosstabN]].iter() {
+        let cells = formulas_of(&ds, sheets, Sheet::CrosstabPerc);
+        assert!(!cells.is_empty());
+        assert_eq!(formula_at(&cells, "E7"), None);
+        assert!(cells.iter().all(|cell| !cell.formula.contains("crosstab(n)")));
+    }
+}
+
+#[test]
+fn workbook_written_in_place_of_output() {
+    let ds = get_test_ds();
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("report.xlsx");
+    std::fs::write(&output, b"previous").unwrap();
+    write_output_file(&ds, &[Sheet::UserGraph], &XlsxOptions::default(), &Theme::default(),
+                      &output).unwrap();
+    // The temp file was renamed over the output, nothing else is left in the directory
+    let entries = std::fs::read_dir(dir.path()).unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect::<Vec<_>>();
+    assert_eq!(entries, vec![std::ffi::OsString::from("report.xlsx")]);
+    assert_eq!(std::fs::read(&output).unwrap(),
+               write_output_bytes(&ds, &[Sheet::UserGraph], &XlsxOptions::default(),
+                                  &Theme::default()).unwrap());
+}
+
+#[test]
+fn html_report_is_self_contained() {
+    let ds = get_test_ds();
+    let mut writer = HtmlReportWriter::new("test");
+    write_report(&mut writer, &ds, &[Sheet::UserGraph, Sheet::CrosstabPerc]).unwrap();
+    let html = writer.html();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.trim_end().ends_with("</html>"));
+    assert_eq!(html.matches("<section").count(), 2);
+    assert_eq!(html.matches("<svg").count(), 16);
+    assert!(html.contains("<th class=\"highlight\">"));
+    assert!(html.contains("<table class=\"space-and-highlight-on-4\">"));
+    assert!(html.contains("<h3 class=\"alert\">"));
+    // No external assets
+    assert!(!html.contains("src="));
+    assert!(!html.contains("<link"));
+}
+
+#[test]
+fn theme_from_json() {
+    let theme = Theme::from_json(r##"{
+        "header": { "background": "#dde7f3", "font_color": "#1F3864" },
+        "chart_palette": ["#1f77b4", "#ff7f0e"],
+        "font": { "name": "Meiryo" }
+    }"##).unwrap();
+    assert_eq!(theme.header, CellStyle::new(0xdde7f3, 0x1f3864));
+    assert_eq!(theme.highlight, Theme::default().highlight);
+    assert_eq!(theme.chart_color(3), Color(0xff7f0e));
+    assert_eq!(theme.font, Some(Font { name: "Meiryo".to_string(), size: 11.0 }));
+    for content in [r#"{ "chart_palette": [] }"#, r#"{ "alert": { "background": "red" } }"#,
+                    r##"{ "colour": "#ffffff" }"##].iter() {
+        assert!(matches!(Theme::from_json(content), Err(RustlyzerError::ThemeWrongFormat(_))));
+    }
+
+    let mut writer = HtmlReportWriter::with_theme("test", &theme);
+    write_report(&mut writer, &get_test_ds(), &[Sheet::UserGraph]).unwrap();
+    let html = writer.html();
+    assert!(html.contains("th.normal { background: #dde7f3; color: #1f3864; }"));
+    assert!(html.contains("font-family: \"Meiryo\", sans-serif;"));
+    assert!(html.contains("fill=\"#ff7f0e\""));
+    assert!(!html.contains("#c6efce"));
+}
+
+#[test]
+fn display_width_counts_wide_characters() {
+    use crate::helpers::strings::display_width;
+
+    assert_eq!(display_width("User-1"), 6);
+    assert_eq!(display_width("ユーザid"), 8);
+    assert_eq!(display_width("東京都（２３区）"), 16);
+    assert_eq!(display_width("ｶﾅ"), 2);
+}
+
+#[test]
+fn raw_data_sheets_freeze_filter_and_size_columns() {
+    let mut writer = RecordingWriter::default();
+    write_report(&mut writer, &get_test_ds(), &[Sheet::FkcRawdata, Sheet::ItRawdata]).unwrap();
+    // 300 respondents below the header row; the IT sheet carries three more columns
+    for (sheet, last_col) in [(Sheet::FkcRawdata, 44), (Sheet::ItRawdata, 47)].iter() {
+        let (_, table, ranges) = writer.tables.iter().find(|(s, _, _)| s == sheet).unwrap();
+        let format = raw_table_format(table, ranges).unwrap();
+        // Panes below the header and right of the two ID columns
+        assert_eq!(format.freeze, CellCoord::new(1, 2), "{:?}", sheet);
+        assert_eq!(format.filter, CellRange::new(0, 0, 300, *last_col), "{:?}", sheet);
+        assert_eq!(format.widths.len(), *last_col as usize + 1, "{:?}", sheet);
+        assert!(format.widths.iter().enumerate().all(|(i, (col, _))| *col as usize == i));
+        assert!(format.widths.iter().all(|(_, width)| (6.0..=50.0).contains(width)));
+        // Longest cell plus room for the filter button, capped at 50 and at least 6
+        let widths = format.widths.iter().map(|(_, width)| *width).collect::<Vec<f64>>();
+        assert_eq!(&widths[..3], &[50.0, 43.0, 18.0], "{:?}", sheet);
+        assert!(widths.contains(&6.0), "{:?}", sheet);
+    }
+}
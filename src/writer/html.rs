@@ -0,0 +1,341 @@
+use super::report::{write_report, CellCoord, CellRange, ChartKind, ChartSpec, Heading, HeadingStyle,
+                    IndexEntry, LaidOutCell, Placement, ReportWriter, SheetLayout, TableRanges};
+use super::theme::Theme;
+use super::Sheet;
+use crate::ds::table::{CellValue, SpecialCase, TableWithMeta};
+use crate::ds::DataSet;
+use crate::errors::RustlyzerError;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// Page style, with the colours and font of `theme`.
+fn style(theme: &Theme) -> String {
+    let font = match &theme.font {
+        Some(font) => format!("\"{}\", sans-serif", font.name.replace('"', "")),
+        None => "sans-serif".to_string(),
+    };
+    let (header, highlight, alert) = (&theme.header, &theme.highlight, &theme.alert);
+    format!("\
+body {{ font-family: {font}; margin: 1em; color: #222; }}
+section {{ margin-bottom: 3em; }}
+.table-wrap {{ overflow-x: auto; margin-bottom: 1.5em; }}
+table {{ border-collapse: collapse; font-size: 0.85em; }}
+th, td {{ border: 1px solid #ddd; padding: 0.2em 0.5em; white-space: nowrap; }}
+th.normal {{ background: {header_bg}; color: {header_fg}; }}
+th.highlight, td.highlight {{ background: {highlight_bg}; color: {highlight_fg}; }}
+table.space-and-highlight-on-4 {{ margin-top: 1.5em; }}
+td.num, td.perc {{ text-align: right; }}
+p.meta {{ margin: 0.1em 0; font-size: 0.85em; }}
+h3.highlight {{ background: {highlight_bg}; color: {highlight_fg}; padding: 0.2em 0.5em; }}
+h3.alert {{ background: {alert_bg}; color: {alert_fg}; padding: 0.2em 0.5em; }}
+figure {{ display: inline-block; vertical-align: top; margin: 0.5em; }}
+figcaption {{ font-weight: bold; text-align: center; }}
+svg text {{ font-size: 11px; }}
+",
+            font = font,
+            header_bg = header.background.css(), header_fg = header.font_color.css(),
+            highlight_bg = highlight.background.css(), highlight_fg = highlight.font_color.css(),
+            alert_bg = alert.background.css(), alert_fg = alert.font_color.css())
+}
+
+/// `ReportWriter` building a single self-contained html page.
+///
+/// Charts are drawn as inline svg from the cells their ranges point at, so the page has no
+/// external assets.
+pub struct HtmlReportWriter {
+    html: String,
+    /// Sheet of the open section
+    sheet: Option<Sheet>,
+    /// Position of the index list in `html`, inserted once every section is written
+    index_at: Option<usize>,
+    layout: SheetLayout,
+    /// Cells written on the current sheet, as laid out in the xlsx output
+    cells: HashMap<CellCoord, CellValue>,
+    theme: Theme,
+}
+
+impl HtmlReportWriter {
+    pub fn new(title: &str) -> Self {
+        Self::with_theme(title, &Theme::default())
+    }
+
+    pub fn with_theme(title: &str, theme: &Theme) -> Self {
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+        let _ = writeln!(html, "<title>{}</title>", escape(title));
+        let _ = writeln!(html, "<style>\n{}</style>\n</head>\n<body>", style(theme));
+        HtmlReportWriter { html, sheet: None, index_at: None, layout: SheetLayout::new(),
+            cells: HashMap::new(), theme: theme.clone() }
+    }
+
+    /// The page, complete once `finish` was called.
+    pub fn html(&self) -> &str {
+        &self.html
+    }
+
+    fn close_sheet(&mut self) {
+        if self.sheet.take().is_some() {
+            self.html.push_str("</section>\n");
+        }
+    }
+
+    fn range_cells(&self, range: CellRange) -> Vec<&CellValue> {
+        static EMPTY: CellValue = CellValue::Empty;
+        let mut values = Vec::<&CellValue>::new();
+        for row in range.first.row..=range.last.row {
+            for col in range.first.col..=range.last.col {
+                values.push(self.cells.get(&CellCoord::new(row, col)).unwrap_or(&EMPTY));
+            }
+        }
+        values
+    }
+}
+
+impl ReportWriter for HtmlReportWriter {
+    fn begin_sheet(&mut self, sheet: Sheet) -> Result<(), RustlyzerError> {
+        self.close_sheet();
+        let _ = writeln!(self.html, "<section id=\"{}\">\n<h2>{}</h2>", sheet.id(),
+                         escape(sheet.sheet_name()));
+        if let Sheet::Index = sheet {
+            self.index_at = Some(self.html.len());
+        }
+        self.sheet = Some(sheet);
+        self.layout = SheetLayout::new();
+        self.cells.clear();
+        Ok(())
+    }
+
+    fn write_table(&mut self, table: &TableWithMeta, placement: Placement)
+        -> Result<TableRanges, RustlyzerError> {
+        let cells = &mut self.cells;
+        let ranges = self.layout.place(table, placement, |coord, cell| {
+            let value = match cell {
+                LaidOutCell::Meta(text) => CellValue::from(text),
+                LaidOutCell::Header(header) => CellValue::from(header.text.as_str()),
+                LaidOutCell::Content { value, .. } | LaidOutCell::Footer(value) => value.clone(),
+            };
+            cells.insert(coord, value);
+            Ok(())
+        })?;
+
+        let html = &mut self.html;
+        match self.sheet {
+            // Tables in the top-left corner are reached through the section id
+            Some(sheet) if ranges.start != CellCoord::default() => {
+                let _ = writeln!(html, "<div class=\"table-wrap\" id=\"{}\">",
+                                 anchor(sheet, ranges.start));
+            },
+            _ => html.push_str("<div class=\"table-wrap\">\n"),
+        }
+        for meta in table.meta.iter() {
+            let _ = writeln!(html, "<p class=\"meta\">{}</p>", escape(meta));
+        }
+        let cols = &table.table.cols;
+        match table.special_case {
+            SpecialCase::None => html.push_str("<table>\n<thead><tr>"),
+            SpecialCase::SpaceAndHighlightOn4 =>
+                html.push_str("<table class=\"space-and-highlight-on-4\">\n<thead><tr>"),
+        }
+        for col in cols.iter() {
+            let class = if col.header.highlight { "highlight" } else { "normal" };
+            let _ = write!(html, "<th class=\"{}\">{}</th>", class, escape(&col.header.text));
+        }
+        html.push_str("</tr></thead>\n<tbody>\n");
+        let row_count = cols.iter().map(|col| col.contents.len()).max().unwrap_or(0);
+        for row in 0..row_count {
+            html.push_str("<tr>");
+            for (col_index, col) in cols.iter().enumerate() {
+                let value = col.contents.get(row).unwrap_or(&CellValue::Empty);
+                let highlight = match table.special_case {
+                    SpecialCase::SpaceAndHighlightOn4 => col_index == 3,
+                    SpecialCase::None => false,
+                };
+                push_cell(html, value, highlight);
+            }
+            html.push_str("</tr>\n");
+        }
+        html.push_str("</tbody>\n");
+        if cols.iter().any(|col| col.footer.is_some()) {
+            html.push_str("<tfoot><tr>");
+            for col in cols.iter() {
+                push_cell(html, col.footer.as_ref().unwrap_or(&CellValue::Empty), false);
+            }
+            html.push_str("</tr></tfoot>\n");
+        }
+        html.push_str("</table>\n</div>\n");
+        Ok(ranges)
+    }
+
+    fn write_heading(&mut self, heading: &Heading) -> Result<(), RustlyzerError> {
+        let class = match heading.style {
+            HeadingStyle::Highlight => "highlight",
+            HeadingStyle::Alert => "alert",
+        };
+        let _ = writeln!(self.html, "<h3 class=\"{}\">{}</h3>", class, escape(&heading.text));
+        Ok(())
+    }
+
+    fn write_chart(&mut self, chart: &ChartSpec) -> Result<(), RustlyzerError> {
+        let labels = self.range_cells(chart.categories)
+            .into_iter()
+            .map(|value| value.to_string())
+            .collect::<Vec<String>>();
+        let values = self.range_cells(chart.values)
+            .into_iter()
+            .map(|value| value.as_f64().unwrap_or(0.0))
+            .collect::<Vec<f64>>();
+        let svg = match chart.kind {
+            ChartKind::Pie => pie_svg(&labels, &values, &self.theme, chart.fill),
+            ChartKind::Bar => bar_svg(&labels, &values, &self.theme, chart.fill),
+        };
+        let _ = writeln!(self.html, "<figure>\n<figcaption>{}</figcaption>\n{}</figure>",
+                         escape(&chart.title), svg);
+        Ok(())
+    }
+
+    fn write_index(&mut self, entries: &[IndexEntry]) -> Result<(), RustlyzerError> {
+        let index_at = self.index_at.ok_or_else(|| RustlyzerError::InternalError(
+            "index sheet not started".to_string()))?;
+        let mut list = String::from("<div class=\"table-wrap\">\n<table>\n<thead><tr>");
+        for header in ["シート", "項目", "ラベル"].iter() {
+            let _ = write!(list, "<th class=\"normal\">{}</th>", header);
+        }
+        list.push_str("</tr></thead>\n<tbody>\n");
+        for entry in entries.iter() {
+            let _ = writeln!(list, "<tr><td>{}</td><td><a href=\"#{}\">{}</a></td><td>{}</td></tr>",
+                             escape(entry.sheet.sheet_name()), anchor(entry.sheet, entry.start),
+                             escape(&entry.title), escape(&entry.label));
+        }
+        list.push_str("</tbody>\n</table>\n</div>\n");
+        self.html.insert_str(index_at, &list);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), RustlyzerError> {
+        self.close_sheet();
+        self.html.push_str("</body>\n</html>\n");
+        Ok(())
+    }
+}
+
+/// Element id of the table starting at `start` on `sheet`.
+fn anchor(sheet: Sheet, start: CellCoord) -> String {
+    if start == CellCoord::default() {
+        sheet.id().to_string()
+    } else {
+        format!("{}-{}", sheet.id(), start.a1())
+    }
+}
+
+fn push_cell(html: &mut String, value: &CellValue, highlight: bool) {
+    let mut classes = Vec::<&str>::new();
+    match value {
+        CellValue::Integer(_) | CellValue::Float(_) => classes.push("num"),
+        CellValue::Percentage(_) => classes.push("perc"),
+        _ => (),
+    }
+    let text = value.to_string();
+    if highlight {
+        classes.push("highlight");
+    }
+    if classes.is_empty() {
+        let _ = write!(html, "<td>{}</td>", escape(&text));
+    } else {
+        let _ = write!(html, "<td class=\"{}\">{}</td>", classes.join(" "), escape(&text));
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Pie of `values`, its slices filled with the chart palette from entry `fill` on.
+fn pie_svg(labels: &[String], values: &[f64], theme: &Theme, fill: usize) -> String {
+    const R: f64 = 80.0;
+    const CX: f64 = 90.0;
+    const CY: f64 = 90.0;
+    let total: f64 = values.iter().sum();
+    let height = (labels.len() as f64 * 16.0 + 10.0).max(180.0);
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"360\" height=\"{}\" \
+                           role=\"img\">\n", height);
+    let mut angle = -PI / 2.0;
+    for (i, value) in values.iter().enumerate() {
+        let color = theme.chart_color(fill + i).css();
+        if total <= 0.0 || *value <= 0.0 {
+            continue;
+        }
+        let sweep = value / total * 2.0 * PI;
+        if sweep >= 2.0 * PI - 1e-9 {
+            let _ = writeln!(svg, "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\" \
+                                   stroke=\"#fff\"/>", CX, CY, R, color);
+        } else {
+            let (x1, y1) = (CX + R * angle.cos(), CY + R * angle.sin());
+            let (x2, y2) = (CX + R * (angle + sweep).cos(), CY + R * (angle + sweep).sin());
+            let large_arc = if sweep > PI { 1 } else { 0 };
+            let _ = writeln!(svg, "<path d=\"M{:.2},{:.2} L{:.2},{:.2} A{},{} 0 {} 1 {:.2},{:.2} Z\" \
+                                   fill=\"{}\" stroke=\"#fff\"/>",
+                             CX, CY, x1, y1, R, R, large_arc, x2, y2, color);
+        }
+        angle += sweep;
+    }
+    for (i, label) in labels.iter().enumerate() {
+        let value = values.get(i).cloned().unwrap_or(0.0);
+        let perc = if total > 0.0 { value / total * 100.0 } else { 0.0 };
+        let y = 14.0 + i as f64 * 16.0;
+        let _ = writeln!(svg, "<rect x=\"190\" y=\"{}\" width=\"10\" height=\"10\" \
+                               fill=\"{}\" stroke=\"#999\"/><text x=\"205\" y=\"{}\">{} \
+                               ({:.1}%)</text>",
+                         y - 9.0, theme.chart_color(fill + i).css(), y, escape(label), perc);
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn bar_svg(labels: &[String], values: &[f64], theme: &Theme, fill: usize) -> String {
+    const LABEL_WIDTH: f64 = 130.0;
+    const BAR_WIDTH: f64 = 200.0;
+    const ROW: f64 = 18.0;
+    let max = values.iter().cloned().fold(0.0, f64::max);
+    let height = labels.len() as f64 * ROW + 10.0;
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+                           role=\"img\">\n", LABEL_WIDTH + BAR_WIDTH + 50.0, height);
+    for (i, label) in labels.iter().enumerate() {
+        let value = values.get(i).cloned().unwrap_or(0.0);
+        let width = if max > 0.0 { value / max * BAR_WIDTH } else { 0.0 };
+        let y = 5.0 + i as f64 * ROW;
+        let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>\
+                               <rect x=\"{}\" y=\"{}\" width=\"{:.2}\" height=\"{}\" \
+                               fill=\"{}\" stroke=\"#999\"/><text x=\"{:.2}\" y=\"{}\">{}</text>",
+                         LABEL_WIDTH - 5.0, y + 12.0, escape(label), LABEL_WIDTH, y + 2.0, width,
+                         ROW - 4.0, theme.chart_color(fill).css(), LABEL_WIDTH + width + 4.0,
+                         y + 12.0, value);
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Write the html report for an already loaded `dataset` to `output_path`.
+///
+/// Raw data sheets are left out of the page.
+pub fn write_html_file(dataset: &DataSet, sheets: &[Sheet], theme: &Theme, output_path: &Path)
+    -> Result<(), RustlyzerError> {
+    let sheets = sheets
+        .iter()
+        .cloned()
+        .filter(|sheet| !matches!(sheet, Sheet::FkcRawdata | Sheet::ItRawdata))
+        .collect::<Vec<Sheet>>();
+    let title = output_path.file_stem().map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "report".to_string());
+    let mut writer = HtmlReportWriter::with_theme(&title, theme);
+    write_report(&mut writer, dataset, &sheets)?;
+    File::create(output_path)?.write_all(writer.html().as_bytes())?;
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use super::report::{write_report, ChartSpec, Heading, IndexEntry, Placement, ReportWriter,
+                    SheetLayout, TableRanges};
+use super::Sheet;
+use crate::ds::table::TableWithMeta;
+use crate::ds::DataSet;
+use crate::errors::RustlyzerError;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// Every table of a report, grouped by the sheet it appears on in the xlsx output.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JsonReport {
+    pub sections: Vec<JsonSection>,
+    /// Positions the tables would take in the xlsx output
+    #[serde(skip)]
+    layout: SheetLayout,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JsonSection {
+    pub sheet: Sheet,
+    /// Sheet name used in the xlsx output
+    pub name: String,
+    pub tables: Vec<TableWithMeta>,
+    /// Entries of the index sheet
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub index: Vec<IndexEntry>,
+}
+
+impl JsonReport {
+    /// Compute the tables of `sheets`, in the given order.
+    pub fn from_dataset(dataset: &DataSet, sheets: &[Sheet]) -> Result<Self, RustlyzerError> {
+        let mut report = JsonReport { sections: Vec::<JsonSection>::with_capacity(sheets.len()),
+            layout: SheetLayout::new() };
+        write_report(&mut report, dataset, sheets)?;
+        Ok(report)
+    }
+
+    pub fn to_json(&self) -> Result<String, RustlyzerError> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| RustlyzerError::InternalError(e.to_string()))
+    }
+}
+
+/// Collects the tables, headings and charts are left out.
+impl ReportWriter for JsonReport {
+    fn begin_sheet(&mut self, sheet: Sheet) -> Result<(), RustlyzerError> {
+        self.sections.push(JsonSection { sheet, name: sheet.sheet_name().to_string(),
+            tables: Vec::<TableWithMeta>::new(), index: Vec::<IndexEntry>::new() });
+        self.layout = SheetLayout::new();
+        Ok(())
+    }
+
+    fn write_table(&mut self, table: &TableWithMeta, placement: Placement)
+        -> Result<TableRanges, RustlyzerError> {
+        match self.sections.last_mut() {
+            Some(section) => section.tables.push(table.clone()),
+            None => return Err(RustlyzerError::InternalError("no sheet started".to_string())),
+        }
+        self.layout.place(table, placement, |_, _| Ok(()))
+    }
+
+    fn write_heading(&mut self, _heading: &Heading) -> Result<(), RustlyzerError> {
+        Ok(())
+    }
+
+    fn write_chart(&mut self, _chart: &ChartSpec) -> Result<(), RustlyzerError> {
+        Ok(())
+    }
+
+    fn write_index(&mut self, entries: &[IndexEntry]) -> Result<(), RustlyzerError> {
+        match self.sections.iter_mut().find(|section| section.sheet == Sheet::Index) {
+            Some(section) => section.index = entries.to_vec(),
+            None => return Err(RustlyzerError::InternalError("index sheet not started"
+                .to_string())),
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), RustlyzerError> {
+        Ok(())
+    }
+}
+
+/// Write the json report for an already loaded `dataset` to `output_path`.
+pub fn write_json_file(dataset: &DataSet, sheets: &[Sheet], output_path: &Path) -> Result<(),
+    RustlyzerError> {
+    let report = JsonReport::from_dataset(dataset, sheets)?;
+    File::create(output_path)?.write_all(report.to_json()?.as_bytes())?;
+    Ok(())
+}
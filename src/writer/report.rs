@@ -0,0 +1,341 @@
+use super::Sheet;
+use crate::ds::table::{CellValue, Header, SpecialCase, Table, TableWithMeta};
+use crate::ds::{CrosstabType, DataSet};
+use crate::errors::RustlyzerError;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use xlsxwriter::{WorksheetCol, WorksheetRow};
+
+/// Output backend of a report.
+///
+/// `write_report` drives a writer sheet by sheet; layout hints (`Placement`, chart anchors)
+/// are given in worksheet cells and can be ignored by backends without a grid.
+pub trait ReportWriter {
+    /// Start `sheet`. Following tables, headings and charts belong to it.
+    fn begin_sheet(&mut self, sheet: Sheet) -> Result<(), RustlyzerError>;
+    /// Write a table, meta lines first, at `placement` on the current sheet.
+    ///
+    /// Returns the cells the table was written to, for charts over its columns.
+    fn write_table(&mut self, table: &TableWithMeta, placement: Placement)
+        -> Result<TableRanges, RustlyzerError>;
+    /// Write a heading above a group of charts.
+    fn write_heading(&mut self, heading: &Heading) -> Result<(), RustlyzerError>;
+    fn write_chart(&mut self, chart: &ChartSpec) -> Result<(), RustlyzerError>;
+    /// Fill the index sheet, begun before the sections it lists, once every sheet was written.
+    fn write_index(&mut self, entries: &[IndexEntry]) -> Result<(), RustlyzerError>;
+    /// Complete the output after the last sheet.
+    fn finish(&mut self) -> Result<(), RustlyzerError>;
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CellCoord {
+    pub row: WorksheetRow,
+    pub col: WorksheetCol,
+}
+
+impl CellCoord {
+    pub fn new(row: WorksheetRow, col: WorksheetCol) -> Self {
+        CellCoord { row, col }
+    }
+
+    /// A1 style name of the cell, e.g. `AB12`.
+    pub fn a1(&self) -> String {
+        let mut col_name = Vec::<u8>::new();
+        let mut col = self.col as u32 + 1;
+        while col > 0 {
+            col_name.push(b'A' + ((col - 1) % 26) as u8);
+            col = (col - 1) / 26;
+        }
+        col_name.reverse();
+        format!("{}{}", String::from_utf8_lossy(&col_name), self.row + 1)
+    }
+}
+
+/// Inclusive block of cells on the current sheet.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CellRange {
+    pub first: CellCoord,
+    pub last: CellCoord,
+}
+
+impl CellRange {
+    pub fn new(first_row: WorksheetRow, first_col: WorksheetCol, last_row: WorksheetRow,
+               last_col: WorksheetCol) -> Self {
+        CellRange { first: CellCoord::new(first_row, first_col),
+            last: CellCoord::new(last_row, last_col) }
+    }
+}
+
+/// Cells a table was written to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableRanges {
+    /// Top-left cell of the table
+    pub start: CellCoord,
+    /// Content cells of each column, header and footer excluded
+    pub columns: Vec<CellRange>,
+    /// Row of the footers, below the longest column
+    pub footer_row: WorksheetRow,
+}
+
+impl TableRanges {
+    pub fn column(&self, index: usize) -> Result<CellRange, RustlyzerError> {
+        self.columns.get(index).copied().ok_or_else(|| RustlyzerError::InternalError(
+            format!("table has no column {}", index)))
+    }
+}
+
+/// Where a table goes relative to the previous table on the sheet.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Placement {
+    /// Top-left cell of the table
+    At(CellCoord),
+    /// In the same column as the previous table, `n` rows below the row it ended on
+    Below(WorksheetRow),
+    /// At the top of the sheet, `n` columns right of the previous table
+    Right(WorksheetCol),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HeadingStyle {
+    Highlight,
+    Alert,
+}
+
+#[derive(Debug, Clone)]
+pub struct Heading {
+    pub text: String,
+    pub anchor: CellCoord,
+    /// Number of columns the heading spans
+    pub width: WorksheetCol,
+    pub style: HeadingStyle,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChartKind {
+    Pie,
+    Bar,
+}
+
+/// Single series chart over cells of the current sheet.
+#[derive(Debug, Clone)]
+pub struct ChartSpec {
+    pub kind: ChartKind,
+    pub title: String,
+    pub categories: CellRange,
+    pub values: CellRange,
+    /// Entry of the theme's chart palette filling the series. Html pies fill their slices with
+    /// the entries from this one on.
+    pub fill: usize,
+    /// Top-left cell of the chart
+    pub anchor: CellCoord,
+}
+
+/// Section of the report listed on the index sheet.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IndexEntry {
+    pub sheet: Sheet,
+    /// Field title, or the sheet name for an entry covering the whole sheet
+    pub title: String,
+    pub label: String,
+    /// Top-left cell of the section
+    pub start: CellCoord,
+}
+
+impl IndexEntry {
+    fn sheet(sheet: Sheet) -> Self {
+        IndexEntry { sheet, title: sheet.sheet_name().to_string(), label: String::new(),
+            start: CellCoord::new(0, 0) }
+    }
+}
+
+/// Cell of a table, as positioned by `SheetLayout`.
+pub enum LaidOutCell<'a> {
+    Meta(&'a str),
+    Header(&'a Header),
+    Content { col_index: usize, value: &'a CellValue, special_case: SpecialCase },
+    Footer(&'a CellValue),
+}
+
+/// Cell positions of tables written one after another on a sheet.
+#[derive(Debug, Default)]
+pub struct SheetLayout {
+    cursor: CellCoord,
+    last_start_col: WorksheetCol,
+}
+
+impl SheetLayout {
+    pub fn new() -> Self {
+        SheetLayout { cursor: CellCoord::new(0, 0), last_start_col: 0 }
+    }
+
+    /// Position `table` at `placement`, calling `cell` for each of its cells, and move past it.
+    ///
+    /// Returns the cells the table was placed on.
+    pub fn place<'a, F>(&mut self, table: &'a TableWithMeta, placement: Placement, mut cell: F)
+        -> Result<TableRanges, RustlyzerError>
+    where F: FnMut(CellCoord, LaidOutCell<'a>) -> Result<(), RustlyzerError> {
+        let start = match placement {
+            Placement::At(coord) => coord,
+            Placement::Below(rows) => CellCoord::new(self.cursor.row + rows, self.last_start_col),
+            Placement::Right(cols) => CellCoord::new(0, self.cursor.col + cols),
+        };
+        let mut row = start.row;
+        let mut columns = Vec::<CellRange>::with_capacity(table.table.cols.len());
+        for meta in table.meta.iter() {
+            cell(CellCoord::new(row, start.col), LaidOutCell::Meta(meta))?;
+            row += 1;
+        }
+        if let SpecialCase::SpaceAndHighlightOn4 = table.special_case {
+            row += 1;
+        }
+        let cols = &table.table.cols;
+        let max_row_content = cols.iter().map(|col| col.contents.len()).max().unwrap_or(0)
+            as WorksheetRow;
+        let footer_row = row + max_row_content + 1;
+        for (col_index, col) in cols.iter().enumerate() {
+            let col_no = start.col + col_index as WorksheetCol;
+            columns.push(CellRange::new(row + 1, col_no, row + col.contents.len() as WorksheetRow,
+                                        col_no));
+            cell(CellCoord::new(row, col_no), LaidOutCell::Header(&col.header))?;
+            for (content_index, value) in col.contents.iter().enumerate() {
+                cell(CellCoord::new(row + content_index as WorksheetRow + 1, col_no),
+                     LaidOutCell::Content { col_index, value, special_case: table.special_case })?;
+            }
+            if let Some(footer) = &col.footer {
+                cell(CellCoord::new(footer_row, col_no), LaidOutCell::Footer(footer))?;
+            }
+        }
+        self.cursor = CellCoord::new(footer_row,
+                                     start.col + cols.len() as WorksheetCol);
+        self.last_start_col = start.col;
+        Ok(TableRanges { start, columns, footer_row })
+    }
+}
+
+/// Write the `sheets` of the report for `dataset`, in the given order, and finish `writer`.
+///
+/// The index sheet lists the other sheets, each aggregate field and each crosstab base field,
+/// at the cells their tables were written to.
+pub fn write_report(writer: &mut dyn ReportWriter, dataset: &DataSet, sheets: &[Sheet])
+    -> Result<(), RustlyzerError> {
+    let mut index = Vec::<IndexEntry>::new();
+    for &sheet in sheets.iter() {
+        writer.begin_sheet(sheet)?;
+        if sheet != Sheet::Index {
+            index.push(IndexEntry::sheet(sheet));
+        }
+        match sheet {
+            // Filled once the sections it links to are written
+            Sheet::Index => (),
+            Sheet::FkcRawdata => {
+                let table = without_meta(dataset.get_fkc_raw_table()?);
+                writer.write_table(&table, Placement::At(CellCoord::new(0, 0)))?;
+            },
+            Sheet::ItRawdata => {
+                let table = without_meta(dataset.get_it_raw_table()?);
+                writer.write_table(&table, Placement::At(CellCoord::new(0, 0)))?;
+            },
+            Sheet::UserGraph => write_user_graph(writer, dataset)?,
+            Sheet::Aggregate => {
+                let mut placement = Placement::At(CellCoord::new(0, 0));
+                for table in dataset.get_aggregate_tables()?.iter() {
+                    let ranges = writer.write_table(table, placement)?;
+                    // Meta lines: title, type, label
+                    let meta = |i: usize| table.meta.get(i).cloned().unwrap_or_default();
+                    index.push(IndexEntry { sheet, title: meta(0), label: meta(2),
+                        start: ranges.start });
+                    placement = Placement::Right(2);
+                }
+            },
+            Sheet::CrosstabN | Sheet::CrosstabPerc => {
+                let crosstab_type = if let Sheet::CrosstabN = sheet { CrosstabType::N }
+                    else { CrosstabType::Perc };
+                let mut placement = Placement::At(CellCoord::new(0, 0));
+                for table in dataset.get_crosstab_tables(crosstab_type)?.iter() {
+                    let ranges = writer.write_table(table, placement)?;
+                    // Each base field starts with its totals block, headed by title and label
+                    if let SpecialCase::SpaceAndHighlightOn4 = table.special_case {
+                        let header = |i: usize| table.table.cols.get(i)
+                            .map(|col| col.header.text.to_owned()).unwrap_or_default();
+                        index.push(IndexEntry { sheet, title: header(0), label: header(2),
+                            start: ranges.start });
+                    }
+                    placement = Placement::Below(0);
+                }
+            },
+            Sheet::ReportInfo => {
+                let table = without_meta(dataset.get_report_info_table(Local::now().naive_local()));
+                writer.write_table(&table, Placement::At(CellCoord::new(0, 0)))?;
+            },
+            Sheet::DataQuality => {
+                let (summary, rows) = dataset.get_data_quality_tables();
+                writer.write_table(&without_meta(summary), Placement::At(CellCoord::new(0, 0)))?;
+                writer.write_table(&without_meta(rows), Placement::Below(2))?;
+            },
+        }
+    }
+    if sheets.contains(&Sheet::Index) {
+        writer.write_index(&index)?;
+    }
+    writer.finish()
+}
+
+fn without_meta(table: Table) -> TableWithMeta {
+    TableWithMeta::new(Vec::<String>::new(), table)
+}
+
+fn write_user_graph(writer: &mut dyn ReportWriter, dataset: &DataSet) -> Result<(),
+    RustlyzerError> {
+    // Tables: two columns of tables on the left of the sheet
+    let mut write = |table: Table, placement: Placement| writer.write_table(&without_meta(table),
+                                                                           placement);
+    let age1060 = write(dataset.get_user_graph_table_age1060()?, Placement::At(CellCoord::new(0, 0)))?;
+    let age1070 = write(dataset.get_user_graph_table_age1070()?, Placement::Below(2))?;
+    let gender = write(dataset.get_user_graph_table_gender()?, Placement::Below(2))?;
+    let marital = write(dataset.get_user_graph_table_marital()?, Placement::Below(2))?;
+    let children = write(dataset.get_user_graph_table_children()?, Placement::Below(2))?;
+    let job = write(dataset.get_user_graph_table_job()?, Placement::At(CellCoord::new(0, 4)))?;
+    let region = write(dataset.get_user_graph_table_region()?, Placement::Below(3))?;
+    let income = write(dataset.get_user_graph_table_income()?, Placement::Below(3))?;
+
+    // Charts: the same charts with and without n-number, each as two columns of charts.
+    // Pies are over (label, count) columns, bars over (graph label, percentage) columns.
+    const PIE: (usize, usize) = (0, 1);
+    const BAR: (usize, usize) = (2, 3);
+    let groups = [
+        (9, "n数あり 未既婚/子供の人数/世帯年収", "n数あり　年代/性別/職業/地域",
+         HeadingStyle::Highlight),
+        (28, "n数なし　未既婚/子供の人数/世帯年収", "n数なし　年代/性別/職業/地域",
+         HeadingStyle::Alert),
+    ];
+    for &(starting_col, heading_1, heading_2, style) in groups.iter() {
+        // -- First column --
+        let charts = vec![
+            (ChartKind::Pie, "未既婚", &marital, PIE, 1),
+            (ChartKind::Pie, "子供の人数", &children, PIE, 16),
+            (ChartKind::Bar, "世帯年収", &income, BAR, 31),
+        ];
+        // -- Second column --
+        let charts_2 = vec![
+            (ChartKind::Pie, "年代", &age1060, PIE, 1),
+            (ChartKind::Pie, "性別", &gender, PIE, 16),
+            (ChartKind::Pie, "年代", &age1070, PIE, 31),
+            (ChartKind::Bar, "職業", &job, BAR, 46),
+            (ChartKind::Bar, "地域", &region, BAR, 61),
+        ];
+        // Each chart of a group has its own entry of the palette, the same in both groups
+        let mut fill = 0;
+        for &(heading, col, ref charts) in [(heading_1, starting_col, charts),
+                                            (heading_2, starting_col + 9, charts_2)].iter() {
+            writer.write_heading(&Heading { text: heading.to_string(),
+                anchor: CellCoord::new(0, col), width: 8, style })?;
+            for &(kind, title, ranges, (categories, values), row) in charts.iter() {
+                writer.write_chart(&ChartSpec { kind, title: title.to_string(),
+                    categories: ranges.column(categories)?, values: ranges.column(values)?,
+                    fill, anchor: CellCoord::new(row, col) })?;
+                fill += 1;
+            }
+        }
+    }
+    Ok(())
+}
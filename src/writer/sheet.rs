@@ -0,0 +1,86 @@
+use crate::errors::RustlyzerError;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Worksheets a report can contain.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Sheet {
+    /// Links to every section of the other sheets
+    Index,
+    FkcRawdata,
+    ItRawdata,
+    UserGraph,
+    Aggregate,
+    CrosstabN,
+    CrosstabPerc,
+    /// Input files, config and record counts the report was generated from
+    ReportInfo,
+    /// Input rows left out by a lenient read, with the reason for each
+    DataQuality,
+}
+
+impl Sheet {
+    /// Name of the worksheet inside the workbook.
+    pub fn sheet_name(&self) -> &'static str {
+        match self {
+            Sheet::Index => "index",
+            Sheet::FkcRawdata => "fkc_rawdata",
+            Sheet::ItRawdata => "it_rawdata",
+            Sheet::UserGraph => "user_graph",
+            Sheet::Aggregate => "aggregate",
+            Sheet::CrosstabN => "crosstab(n)",
+            Sheet::CrosstabPerc => "crosstab(%)",
+            Sheet::ReportInfo => "report_info",
+            Sheet::DataQuality => "data_quality",
+        }
+    }
+
+    /// Sheets written by default, in order. The index and data-quality sheets are only written
+    /// when selected.
+    pub fn get_all() -> Vec<Sheet> {
+        vec![
+            Sheet::FkcRawdata,
+            Sheet::ItRawdata,
+            Sheet::UserGraph,
+            Sheet::Aggregate,
+            Sheet::CrosstabN,
+            Sheet::CrosstabPerc,
+            Sheet::ReportInfo,
+        ]
+    }
+
+    /// Identifier used for the sheet in job files.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Sheet::Index => "index",
+            Sheet::FkcRawdata => "fkc_rawdata",
+            Sheet::ItRawdata => "it_rawdata",
+            Sheet::UserGraph => "user_graph",
+            Sheet::Aggregate => "aggregate",
+            Sheet::CrosstabN => "crosstab_n",
+            Sheet::CrosstabPerc => "crosstab_perc",
+            Sheet::ReportInfo => "report_info",
+            Sheet::DataQuality => "data_quality",
+        }
+    }
+}
+
+/// Parse a sheet from its id (`crosstab_n`) or worksheet name (`crosstab(n)`).
+impl FromStr for Sheet {
+    type Err = RustlyzerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut all = vec![Sheet::Index];
+        all.append(&mut Sheet::get_all());
+        all.push(Sheet::DataQuality);
+        all.iter()
+            .find(|sheet| sheet.id() == s || sheet.sheet_name() == s)
+            .copied()
+            .ok_or_else(|| RustlyzerError::InvalidConfigValError {
+                config_item: "sheets".to_string(),
+                val: s.to_string(),
+                expected_values: all.iter().map(|sheet| sheet.id().to_string()).collect(),
+            })
+    }
+}
@@ -0,0 +1,133 @@
+use crate::errors::RustlyzerError;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Colour as 0xRRGGBB, written `"#rrggbb"` in theme files.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Color(pub u32);
+
+impl Color {
+    /// `#rrggbb` notation, as used in css.
+    pub fn css(&self) -> String {
+        format!("#{:06x}", self.0)
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.css())
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let hex = s.strip_prefix('#').unwrap_or(&s);
+        if hex.len() != 6 {
+            return Err(de::Error::custom(format!("expected a #rrggbb colour, got {:?}", s)));
+        }
+        u32::from_str_radix(hex, 16)
+            .map(Color)
+            .map_err(|_| de::Error::custom(format!("expected a #rrggbb colour, got {:?}", s)))
+    }
+}
+
+/// Background and font colour of a cell.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CellStyle {
+    pub background: Color,
+    pub font_color: Color,
+}
+
+impl CellStyle {
+    pub fn new(background: u32, font_color: u32) -> Self {
+        CellStyle { background: Color(background), font_color: Color(font_color) }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Font {
+    pub name: String,
+    #[serde(default = "default_font_size")]
+    pub size: f64,
+}
+
+fn default_font_size() -> f64 {
+    11.0
+}
+
+/// Colours, formats and font of a report, applied to every sheet and chart.
+///
+/// Loaded from json; entries left out keep their default. Number formats are Excel format
+/// codes and only apply to workbooks.
+///
+/// ```json
+/// {
+///     "header": { "background": "#dde7f3", "font_color": "#1f3864" },
+///     "chart_palette": ["#1f77b4", "#ff7f0e", "#2ca02c"],
+///     "percentage_format": "0.0\\%",
+///     "font": { "name": "Meiryo", "size": 10 }
+/// }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// Column headers
+    pub header: CellStyle,
+    /// Highlighted headers and cells, and chart headings
+    pub highlight: CellStyle,
+    /// Alert chart headings and crosstab(%) cells far from their column total
+    pub alert: CellStyle,
+    /// Chart fills, in order
+    pub chart_palette: Vec<Color>,
+    pub number_format: String,
+    pub percentage_format: String,
+    pub date_format: String,
+    /// Font of every cell, the spreadsheet default if not set
+    pub font: Option<Font>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            header: CellStyle::new(0xc6efce, 0x006100),
+            highlight: CellStyle::new(0xffeb9c, 0x9c6500),
+            alert: CellStyle::new(0xffc7ce, 0x9c0006),
+            chart_palette: vec![0xccffff, 0xffe699, 0xffff99, 0xffccff, 0xffcc99, 0xcc99ff,
+                                0xd2ff79].into_iter().map(Color).collect(),
+            number_format: "General".to_string(),
+            percentage_format: r#"##.00\%"#.to_string(),
+            date_format: "yyyy/mm/dd hh:mm".to_string(),
+            font: None,
+        }
+    }
+}
+
+impl Theme {
+    pub fn from_json(content: &str) -> Result<Self, RustlyzerError> {
+        let theme = serde_json::from_str::<Theme>(content)
+            .map_err(|e| RustlyzerError::ThemeWrongFormat(e.to_string()))?;
+        if theme.chart_palette.is_empty() {
+            return Err(RustlyzerError::ThemeWrongFormat("chart_palette is empty".to_string()));
+        }
+        Ok(theme)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, RustlyzerError> {
+        let mut content = String::new();
+        File::open(path)?.read_to_string(&mut content)?;
+        Self::from_json(&content)
+    }
+
+    /// Colour `index` of the chart palette, starting over past its end.
+    pub fn chart_color(&self, index: usize) -> Color {
+        match self.chart_palette.len() {
+            0 => Color(0xccffff),
+            len => self.chart_palette[index % len],
+        }
+    }
+}
@@ -0,0 +1,410 @@
+use super::report::{CellCoord, CellRange, ChartKind, ChartSpec, Heading, HeadingStyle, IndexEntry,
+                    LaidOutCell, Placement, ReportWriter, SheetLayout, TableRanges};
+use super::theme::{CellStyle, Theme};
+use super::Sheet;
+use crate::ds::table::{CellRef, CellValue, Formula, SpecialCase, TableWithMeta, Total};
+use crate::errors::RustlyzerError;
+use crate::helpers::strings::display_width;
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use serde::{Deserialize, Serialize};
+use xlsxwriter::{ChartFill, ChartType, DateTime, Format, FormatAlignment, FormatColor,
+                 FormatUnderline, Workbook, Worksheet, WorksheetCol, WorksheetRow};
+
+/// Options of the xlsx output.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct XlsxOptions {
+    /// Write totals and percentages as formulas over the count cells, with the computed values
+    /// cached for viewers that do not recalculate
+    pub formulas: bool,
+}
+
+/// ID columns of raw data sheets, kept in view with the header row
+const RAW_FROZEN_COLS: WorksheetCol = 2;
+/// Bounds of raw data column widths, in characters
+const RAW_MIN_WIDTH: usize = 6;
+const RAW_MAX_WIDTH: usize = 50;
+
+/// `ReportWriter` adding sheets to an xlsx workbook.
+///
+/// The workbook is owned by the caller and has to be closed after `finish`.
+pub struct XlsxReportWriter<'a> {
+    workbook: &'a Workbook,
+    options: XlsxOptions,
+    theme: Theme,
+    sheet: Option<(Sheet, Worksheet<'a>)>,
+    /// Index sheet, filled after the others
+    index_sheet: Option<Worksheet<'a>>,
+    /// Sheets begun so far, in order
+    sheets: Vec<Sheet>,
+    layout: SheetLayout,
+    bg_normal: Format<'a>,
+    bg_highlight: Format<'a>,
+    bg_highlight_red: Format<'a>,
+    num_format: Format<'a>,
+    perc_format: Format<'a>,
+    date_format: Format<'a>,
+    /// Format of cells without one, only set for a theme font
+    text_format: Option<Format<'a>>,
+    link_format: Format<'a>,
+}
+
+impl<'a> XlsxReportWriter<'a> {
+    pub fn new(workbook: &'a Workbook) -> Self {
+        Self::with_options(workbook, XlsxOptions::default(), &Theme::default())
+    }
+
+    pub fn with_options(workbook: &'a Workbook, options: XlsxOptions, theme: &Theme) -> Self {
+        // Every format carries the theme font
+        let add_format = || match &theme.font {
+            Some(font) => workbook.add_format().set_font_name(&font.name)
+                .set_font_size(font.size),
+            None => workbook.add_format(),
+        };
+        let styled = |style: &CellStyle| add_format()
+            .set_bg_color(FormatColor::Custom(style.background.0))
+            .set_font_color(FormatColor::Custom(style.font_color.0));
+        XlsxReportWriter {
+            workbook,
+            options,
+            theme: theme.clone(),
+            sheet: None,
+            index_sheet: None,
+            sheets: Vec::<Sheet>::new(),
+            layout: SheetLayout::new(),
+            bg_normal: styled(&theme.header),
+            bg_highlight: styled(&theme.highlight),
+            bg_highlight_red: styled(&theme.alert),
+            num_format: add_format().set_align(FormatAlignment::Right)
+                .set_num_format(&theme.number_format),
+            perc_format: add_format().set_num_format(&theme.percentage_format),
+            date_format: add_format().set_num_format(&theme.date_format),
+            text_format: theme.font.as_ref().map(|_| add_format()),
+            link_format: add_format().set_underline(FormatUnderline::Single)
+                .set_font_color(FormatColor::Blue),
+        }
+    }
+}
+
+impl<'a> XlsxReportWriter<'a> {
+    /// Overwrite the cells of `table` that have a formula with it, keeping their value cached.
+    fn write_formulas(&mut self, table: &TableWithMeta, ranges: &TableRanges)
+        -> Result<(), RustlyzerError> {
+        let cells = formula_cells(table, ranges, &self.sheets)?;
+        let (num_format, perc_format) = (&self.num_format, &self.perc_format);
+        let (_, worksheet) = current(&mut self.sheet)?;
+        for cell in cells.iter() {
+            let format = match cell.value {
+                CellValue::Percentage(_) => perc_format,
+                _ => num_format,
+            };
+            if let Some(num) = cell.value.as_f64() {
+                worksheet.write_formula_num(cell.coord.row, cell.coord.col, &cell.formula,
+                                            Some(format), num)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Cell of a table written as a formula.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FormulaCell {
+    pub coord: CellCoord,
+    /// A1 formula text
+    pub formula: String,
+    /// Value cached with the formula
+    pub value: CellValue,
+}
+
+/// Cells of `table`, written at `ranges`, to overwrite with their formula. `sheets` are the
+/// sheets begun so far, the current one last.
+///
+/// Cells whose formula refers to cells that were not written keep their value: crosstab(%)
+/// cells refer to the crosstab(n) sheet only when it was written before.
+pub(crate) fn formula_cells(table: &TableWithMeta, ranges: &TableRanges, sheets: &[Sheet])
+    -> Result<Vec<FormulaCell>, RustlyzerError> {
+    let counts_sheet = if sheets.contains(&Sheet::CrosstabN) {
+        Some(Sheet::CrosstabN.sheet_name())
+    } else {
+        None
+    };
+    let refs = FormulaRefs { ranges, counts_sheet };
+    let mut cells = Vec::<FormulaCell>::new();
+    let mut push = |coord: CellCoord, formula: &Formula, col_index: usize, value: &CellValue| {
+        match (refs.formula(formula, col_index), value.as_f64()) {
+            (Some(formula), Some(num)) if num.is_finite() => {
+                cells.push(FormulaCell { coord, formula, value: value.clone() });
+            },
+            _ => (),
+        }
+    };
+    for (col_index, col) in table.table.cols.iter().enumerate() {
+        let range = ranges.column(col_index)?;
+        for (row, value) in col.contents.iter().enumerate() {
+            if let Some(formula) = col.formula(row) {
+                push(CellCoord::new(range.first.row + row as WorksheetRow, range.first.col),
+                     formula, col_index, value);
+            }
+        }
+        if let (Some(formula), Some(value)) = (&col.footer_formula, &col.footer) {
+            push(CellCoord::new(ranges.footer_row, range.first.col), formula, col_index, value);
+        }
+    }
+    Ok(cells)
+}
+
+/// Resolves the table relative references of formulas to A1 references.
+struct FormulaRefs<'r> {
+    ranges: &'r TableRanges,
+    counts_sheet: Option<&'static str>,
+}
+
+impl<'r> FormulaRefs<'r> {
+    /// Formula text, `None` when it refers to cells that were not written.
+    fn formula(&self, formula: &Formula, col_index: usize) -> Option<String> {
+        match formula {
+            Formula::ColumnSum => {
+                let range = self.ranges.columns.get(col_index)?;
+                if range.last.row < range.first.row {
+                    return None;
+                }
+                Some(format!("=SUM({}:{})", range.first.a1(), range.last.a1()))
+            },
+            Formula::Share { count, total } => {
+                let total = match total {
+                    Total::Constant(n) => n.to_string(),
+                    Total::Footer(col) => CellCoord::new(
+                        self.ranges.footer_row, self.ranges.columns.get(*col)?.first.col).a1(),
+                    Total::RowSum { first, last_col } => {
+                        let last_col = self.ranges.columns.get(*last_col)?.first.col;
+                        let (prefix, first) = self.cell(*first)?;
+                        format!("SUM({}{}:{})", prefix, first.a1(),
+                                CellCoord::new(first.row, last_col).a1())
+                    },
+                };
+                let (prefix, count) = self.cell(*count)?;
+                Some(format!("={}{}*100/{}", prefix, count.a1(), total))
+            },
+        }
+    }
+
+    /// Sheet prefix and coordinate of a referenced cell.
+    fn cell(&self, cell: CellRef) -> Option<(String, CellCoord)> {
+        let (prefix, col, row) = match cell {
+            CellRef::Table { col, row } => (String::new(), col, row),
+            CellRef::Counts { col, row } => (format!("'{}'!", self.counts_sheet?), col, row),
+        };
+        let range = self.ranges.columns.get(col)?;
+        Some((prefix, CellCoord::new(range.first.row + row as WorksheetRow, range.first.col)))
+    }
+}
+
+fn xlsx_datetime(date: &NaiveDateTime) -> DateTime {
+    DateTime::new(date.year() as i16, date.month() as i8, date.day() as i8, date.hour() as i8,
+                  date.minute() as i8, date.second() as f64)
+}
+
+/// View of a raw data sheet: panes, filter and column widths.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RawTableFormat {
+    /// First cell below and right of the frozen panes
+    pub freeze: CellCoord,
+    /// Header row and content rows of every column
+    pub filter: CellRange,
+    /// Width of each column, in characters
+    pub widths: Vec<(WorksheetCol, f64)>,
+}
+
+/// Freeze the header row and ID columns of a raw data table written at `ranges`, filter its
+/// rows and size its columns to their longest cell. Widths count East Asian wide characters
+/// twice. `None` for a table without columns.
+pub(crate) fn raw_table_format(table: &TableWithMeta, ranges: &TableRanges)
+    -> Option<RawTableFormat> {
+    let (first, last) = (ranges.columns.first()?.first, ranges.columns.last()?.last);
+    let header_row = first.row - 1;
+    let last_row = ranges.columns.iter().map(|range| range.last.row).max().unwrap_or(header_row)
+        .max(header_row);
+    let widths = (first.col..).zip(table.table.cols.iter())
+        .map(|(col_no, col)| {
+            let width = col.contents.iter()
+                .map(|value| display_width(&value.to_string()))
+                .chain(std::iter::once(display_width(&col.header.text)))
+                .max()
+                .unwrap_or(0);
+            // Room for the autofilter button
+            (col_no, (width + 2).max(RAW_MIN_WIDTH).min(RAW_MAX_WIDTH) as f64)
+        })
+        .collect::<Vec<(WorksheetCol, f64)>>();
+    Some(RawTableFormat {
+        freeze: CellCoord::new(header_row + 1, first.col + RAW_FROZEN_COLS),
+        filter: CellRange::new(header_row, first.col, last_row, last.col),
+        widths,
+    })
+}
+
+fn format_raw_table(worksheet: &mut Worksheet, table: &TableWithMeta, ranges: &TableRanges)
+    -> Result<(), RustlyzerError> {
+    let format = match raw_table_format(table, ranges) {
+        Some(format) => format,
+        None => return Ok(()),
+    };
+    worksheet.freeze_panes(format.freeze.row, format.freeze.col);
+    let filter = format.filter;
+    worksheet.autofilter(filter.first.row, filter.first.col, filter.last.row, filter.last.col)?;
+    for &(col_no, width) in format.widths.iter() {
+        worksheet.set_column(col_no, col_no, width, None)?;
+    }
+    Ok(())
+}
+
+fn current<'s, 'a>(sheet: &'s mut Option<(Sheet, Worksheet<'a>)>)
+    -> Result<(Sheet, &'s mut Worksheet<'a>), RustlyzerError> {
+    match sheet.as_mut() {
+        Some((sheet, worksheet)) => Ok((*sheet, worksheet)),
+        None => Err(RustlyzerError::InternalError("no sheet started".to_string())),
+    }
+}
+
+impl<'a> ReportWriter for XlsxReportWriter<'a> {
+    fn begin_sheet(&mut self, sheet: Sheet) -> Result<(), RustlyzerError> {
+        let mut worksheet = self.workbook.add_worksheet(Some(sheet.sheet_name()))?;
+        self.sheets.push(sheet);
+        if let Sheet::ReportInfo = sheet {
+            // Values as wide as a SHA-256 digest
+            worksheet.set_column(0, 0, 20.0, None)?;
+            worksheet.set_column(1, 1, 66.0, None)?;
+        }
+        if let Sheet::DataQuality = sheet {
+            // Row, column, value, reason
+            worksheet.set_column(0, 2, 20.0, None)?;
+            worksheet.set_column(3, 3, 60.0, None)?;
+        }
+        if let Sheet::Index = sheet {
+            self.index_sheet = Some(worksheet);
+            self.sheet = None;
+        } else {
+            self.sheet = Some((sheet, worksheet));
+        }
+        self.layout = SheetLayout::new();
+        Ok(())
+    }
+
+    fn write_table(&mut self, table: &TableWithMeta, placement: Placement)
+        -> Result<TableRanges, RustlyzerError> {
+        let (_, worksheet) = current(&mut self.sheet)?;
+        let (bg_normal, bg_highlight) = (&self.bg_normal, &self.bg_highlight);
+        let (num_format, perc_format) = (&self.num_format, &self.perc_format);
+        let (date_format, text_format) = (&self.date_format, self.text_format.as_ref());
+        // The number format follows the value type, `format` only applies to text and blanks
+        let write_value = |worksheet: &mut Worksheet, coord: CellCoord, value: &CellValue,
+                           format: Option<&Format>| -> Result<(), RustlyzerError> {
+            let (row, col) = (coord.row, coord.col);
+            match value {
+                CellValue::Text(text) => worksheet.write_string(row, col, text,
+                                                                format.or(text_format))?,
+                CellValue::Integer(n) => worksheet.write_number(row, col, *n as f64,
+                                                                Some(num_format))?,
+                CellValue::Float(n) => worksheet.write_number(row, col, *n, Some(num_format))?,
+                CellValue::Percentage(n) => worksheet.write_number(row, col, *n,
+                                                                   Some(perc_format))?,
+                CellValue::Date(date) => worksheet.write_datetime(row, col, &xlsx_datetime(date),
+                                                                  Some(date_format))?,
+                CellValue::Empty => if format.is_some() {
+                    worksheet.write_blank(row, col, format)?
+                },
+            }
+            Ok(())
+        };
+        let ranges = self.layout.place(table, placement, |coord, cell| {
+            match cell {
+                LaidOutCell::Meta(text) => {
+                    worksheet.write_string(coord.row, coord.col, text, text_format)?;
+                },
+                LaidOutCell::Header(header) => {
+                    let format = if header.highlight { bg_highlight } else { bg_normal };
+                    worksheet.write_string(coord.row, coord.col, &header.text, Some(format))?;
+                },
+                LaidOutCell::Content { col_index, value, special_case } => {
+                    let format = match special_case {
+                        SpecialCase::SpaceAndHighlightOn4 if col_index == 3 => Some(bg_highlight),
+                        _ => None,
+                    };
+                    write_value(worksheet, coord, value, format)?;
+                },
+                LaidOutCell::Footer(value) => write_value(worksheet, coord, value, None)?,
+            }
+            Ok(())
+        })?;
+        if let (Sheet::FkcRawdata, worksheet) | (Sheet::ItRawdata, worksheet) =
+            current(&mut self.sheet)? {
+            format_raw_table(worksheet, table, &ranges)?;
+        }
+        if self.options.formulas {
+            self.write_formulas(table, &ranges)?;
+        }
+        Ok(ranges)
+    }
+
+    fn write_heading(&mut self, heading: &Heading) -> Result<(), RustlyzerError> {
+        let format = match heading.style {
+            HeadingStyle::Highlight => &self.bg_highlight,
+            HeadingStyle::Alert => &self.bg_highlight_red,
+        };
+        let (_, worksheet) = current(&mut self.sheet)?;
+        let anchor = heading.anchor;
+        worksheet.merge_range(anchor.row, anchor.col, anchor.row,
+                              anchor.col + heading.width - 1, &heading.text, Some(format))?;
+        Ok(())
+    }
+
+    fn write_chart(&mut self, spec: &ChartSpec) -> Result<(), RustlyzerError> {
+        let (sheet, worksheet) = current(&mut self.sheet)?;
+        let sheet_name = sheet.sheet_name();
+        let mut chart = self.workbook.add_chart(match spec.kind {
+            ChartKind::Pie => ChartType::Pie,
+            ChartKind::Bar => ChartType::Bar,
+        });
+        let mut series = chart.add_series(None, None);
+        series.set_name(&spec.title);
+        let mut fill = ChartFill::new();
+        fill.color = FormatColor::Custom(self.theme.chart_color(spec.fill).0);
+        series.set_fill(&fill);
+        let (categories, values) = (spec.categories, spec.values);
+        series.set_categories(sheet_name, categories.first.row, categories.first.col,
+                              categories.last.row, categories.last.col);
+        series.set_values(sheet_name, values.first.row, values.first.col, values.last.row,
+                          values.last.col);
+        worksheet.insert_chart(spec.anchor.row, spec.anchor.col, &chart)?;
+        Ok(())
+    }
+
+    fn write_index(&mut self, entries: &[IndexEntry]) -> Result<(), RustlyzerError> {
+        let worksheet = self.index_sheet.as_mut().ok_or_else(|| RustlyzerError::InternalError(
+            "index sheet not started".to_string()))?;
+        let text_format = self.text_format.as_ref();
+        for (col, header) in ["シート", "項目", "ラベル"].iter().enumerate() {
+            worksheet.write_string(0, col as WorksheetCol, header, Some(&self.bg_normal))?;
+        }
+        worksheet.set_column(0, 0, 14.0, None)?;
+        worksheet.set_column(1, 2, 40.0, None)?;
+        for (i, entry) in entries.iter().enumerate() {
+            let row = i as WorksheetRow + 1;
+            let url = format!("internal:'{}'!{}", entry.sheet.sheet_name(), entry.start.a1());
+            worksheet.write_string(row, 0, entry.sheet.sheet_name(), text_format)?;
+            // The link shows the url until overwritten with the title
+            worksheet.write_url(row, 1, &url, Some(&self.link_format))?;
+            worksheet.write_string(row, 1, &entry.title, Some(&self.link_format))?;
+            if !entry.label.is_empty() {
+                worksheet.write_string(row, 2, &entry.label, text_format)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), RustlyzerError> {
+        self.sheet = None;
+        self.index_sheet = None;
+        Ok(())
+    }
+}